@@ -1,288 +1,4024 @@
-use color_eyre::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use ratatui::{
-    DefaultTerminal,
-    prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+mod event;
+mod ui;
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, TimeDelta, Utc};
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use crossterm::{
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event,
+    },
+    execute,
+    terminal::{
+        EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode, enable_raw_mode,
+    },
 };
+use ratatui::{DefaultTerminal, layout::Rect, widgets::ListState};
 use rusqlite::Connection;
 
+use crate::{
+    browser,
+    clipboard::{self, Copied},
+    command::Command,
+    config::{Config, StartupFilter, Theme, ThemeName},
+    db::{self, ImportMode, with_transaction},
+    export::{self, ImportedTask},
+    fuzzy::fuzzy_match,
+    input::TextInput,
+    keymap::KeyMap,
+    model::{
+        self, Activity, ActivityEntry, Priority, Recurrence, SortMode, TagStats, TaskColor,
+        TaskCounts, TaskStatus, TodoItem, format_due, normalize_whitespace, parse_category,
+        parse_due_input, parse_tags,
+    },
+};
+
 #[derive(PartialEq, Default)]
 enum Mode {
     #[default]
     Normal,
     Input,
+    Edit,
+    DueDate,
+    Notes,
+    Search,
+    ConfirmDelete,
+    ConfirmClearCompleted,
+    /// Asks before throwing away text typed in the add or edit prompt, returning to the
+    /// prompt on no. `quit` exits the app on yes.
+    ConfirmDiscard {
+        quit: bool,
+        editing: bool,
+    },
+    /// Offers the draft left over from a session that didn't exit cleanly.
+    ConfirmRestore,
+    Help,
+    Stats,
+    /// The activity log, scrolled with the movement keys.
+    Activity,
+    /// The picker for switching between task lists.
+    Lists,
+    Command,
+    ConfirmImport,
+    Visual,
+    /// Only the selected task and its notes fill the screen.
+    Focus,
+}
+
+enum Status {
+    Info(String),
+    Error(String),
 }
 
-struct TodoItem {
-    id: i64,
-    description: String,
-    is_done: bool,
+enum Action {
+    Added(i64),
+    Deleted(TodoItem),
+    Toggled(i64),
+    /// A task's description, tags and category were edited from these.
+    Edited {
+        id: i64,
+        old_description: String,
+        old_tags: Vec<String>,
+        old_category: Option<String>,
+    },
+    /// A task's notes were edited from `old_notes`.
+    NotesEdited {
+        id: i64,
+        old_notes: String,
+    },
+    /// A task moved from `previous` to another status.
+    StatusChanged {
+        id: i64,
+        previous: TaskStatus,
+    },
+    /// A recurring task was completed and archived, and `next_id` was created in its place.
+    Recurred {
+        id: i64,
+        next_id: i64,
+    },
+    /// A reopened recurring task, which redo completes again. Only found on the redo stack.
+    Completed(i64),
 }
 
-impl TodoItem {
-    fn new(id: i64, description: &str) -> Self {
-        Self {
-            id,
-            description: description.to_string(),
-            is_done: false,
+impl Action {
+    /// What the change was, for the "Undid ..." and "Redid ..." messages.
+    fn label(&self) -> &'static str {
+        match self {
+            Action::Added(_) => "add",
+            Action::Deleted(_) => "delete",
+            Action::Toggled(_) | Action::Recurred { .. } | Action::Completed(_) => "toggle",
+            Action::Edited { .. } => "edit",
+            Action::NotesEdited { .. } => "notes edit",
+            Action::StatusChanged { .. } => "status change",
         }
     }
 }
 
+/// A line of the task list: a category heading, the task on a visible row, or the row
+/// standing in for the collapsed completed tasks.
+#[derive(Debug, PartialEq)]
+enum ListRow {
+    /// `None` heads the tasks without a category.
+    Header(Option<String>),
+    Task(usize),
+    /// How many completed tasks are collapsed. Selected as the row after the last task.
+    Completed(usize),
+}
+
+/// The part of the list held in `items` when only a window of it is loaded.
+#[derive(Clone, Copy)]
+struct TaskWindow {
+    /// How many tasks into the full list the window starts.
+    start: usize,
+    /// Counts over the full list, for the footer and progress bar.
+    counts: TaskCounts,
+}
+
+/// Where a search query matched a task, in the order the results are ranked.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum SearchField {
+    Description,
+    Tag,
+    Notes,
+}
+
+const UNDO_LIMIT: usize = 100;
+
+/// How long the footer shows "saved ✓" after a write.
+const SAVED_INDICATOR: Duration = Duration::from_secs(2);
+
+/// How often the screen is redrawn without input, keeping the clock and due dates current.
+const TICK: Duration = Duration::from_secs(1);
+
+/// How soon a task due at a set time has to come up to be called out at startup.
+const REMINDER_WINDOW: TimeDelta = TimeDelta::hours(1);
+
+/// Most tags offered at once while completing one.
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Holds the description being typed into the add prompt, next to the config, so it can
+/// be recovered after a crash.
+const DRAFT_FILE: &str = "input.draft";
+
+const THEME_SETTING: &str = "theme";
+const SELECTED_SETTING: &str = "selected_task";
+const SORT_SETTING: &str = "sort";
+const HIDE_DONE_SETTING: &str = "hide_done";
+/// Empty when no priority filter is on, like [`TAG_FILTER_SETTING`].
+const PRIORITY_FILTER_SETTING: &str = "priority_filter";
+const TAG_FILTER_SETTING: &str = "tag_filter";
+
 pub struct App {
     should_exit: bool,
     items: Vec<TodoItem>,
     state: ListState,
     mode: Mode,
-    input_buffer: String,
+    input_buffer: TextInput,
+    search_buffer: TextInput,
+    tag_filter: Option<String>,
+    marked: HashSet<i64>,
+    show_archived: bool,
+    show_detail: bool,
+    hide_done: bool,
+    /// Completed tasks are folded into a single row at the bottom of the list.
+    completed_collapsed: bool,
+    /// The task selected in the full list when the today view was opened, restored when it
+    /// closes. `None` while the full list is shown.
+    today_view: Option<Option<i64>>,
+    /// Only tasks of this priority are shown.
+    priority_filter: Option<Priority>,
+    sort_mode: SortMode,
+    /// The config's `default_sort` and `default_filter`, which a list's saved settings start
+    /// from.
+    default_sort: SortMode,
+    default_filter: Option<StartupFilter>,
+    status: Option<Status>,
+    undo_stack: Vec<Action>,
+    /// Undone changes, each with the label of the change it redoes.
+    redo_stack: Vec<(Action, &'static str)>,
+    /// Tasks toggled since launch, for the summary printed on exit. Ones toggled back to
+    /// undone are filtered out then.
+    session_toggled: HashSet<i64>,
+    tag_stats: Vec<TagStats>,
+    activity: Vec<ActivityEntry>,
+    activity_state: ListState,
+    /// Every list, the default one first, as of the last time the list picker was opened.
+    list_names: Vec<String>,
+    lists_state: ListState,
+    /// The database the app was opened on, which names the other lists. `None` in memory.
+    home_path: Option<PathBuf>,
+    /// The list `connection` is open on, or `None` for the default list.
+    active_list: Option<String>,
+    /// Consecutive days with a completion, as of the last time the statistics were opened.
+    streak: usize,
+    /// Tasks completed on each day the statistics chart covers, oldest first.
+    completions_per_day: Vec<(NaiveDate, u64)>,
+    chart_days: usize,
+    /// Incomplete tasks due today or earlier, shown in a banner until the first keypress.
+    reminders: Vec<TodoItem>,
+    /// Digits typed in normal mode, applied as a repeat count to the next command.
+    pending_count: Option<usize>,
+    /// Set after a lone `g` while waiting to see whether it becomes `gg`.
+    pending_g: bool,
+    /// How many tasks the pending delete confirmation removes, starting at the selection.
+    delete_count: usize,
+    /// Set by `E` so the run loop, which owns the terminal, hands it to `$EDITOR`.
+    editor_requested: bool,
+    keymap: KeyMap,
+    max_description_length: usize,
+    stale_after_days: u32,
+    line_numbers: bool,
+    large_list_warning: usize,
+    /// Tasks loaded at a time when the list is windowed. Zero loads them all.
+    load_window: usize,
+    /// Which part of the list `items` holds, or `None` when it holds every task.
+    window: Option<TaskWindow>,
+    /// A description that was just flagged as a duplicate, so submitting it again adds it.
+    duplicate_warning: Option<String>,
+    /// The task the add prompt is adding a subtask to.
+    subtask_parent: Option<i64>,
+    /// The contents of the draft file, empty when there is none.
+    draft: String,
+    /// Existing tags that complete the one being typed in the add or edit prompt.
+    tag_suggestions: Vec<String>,
+    /// The highlighted entry of `tag_suggestions`.
+    suggestion: usize,
+    /// When the database last changed, while the footer's saved indicator is showing.
+    saved_at: Option<Instant>,
+    data_dir: PathBuf,
+    theme_name: ThemeName,
+    /// The dark palette with any `[theme]` overrides from the config applied.
+    configured_theme: Theme,
+    theme: Theme,
+    /// Inside of the list border as of the last render, used for page jumps and mouse hits.
+    list_area: Rect,
+    /// The first list line shown as of the last render, counting category headings.
+    list_offset: usize,
+    /// Whether the list shows one page at a time instead of scrolling.
+    paged: bool,
+    /// Running on a throwaway in-memory database, so nothing is kept on exit.
+    demo: bool,
     connection: Connection,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let connection = Connection::open("tasks.db")?;
-
-        connection.execute(
-            "CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                description TEXT NOT NULL,
-                is_done INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        )?;
+    /// Builds the app on an open `connection`, migrating its schema. Config files are read
+    /// from `data_dir`, where exports are also written.
+    pub fn new(connection: Connection, data_dir: PathBuf) -> Result<Self> {
+        // An in-memory database has no journal file to switch.
+        let in_memory = connection.path().is_none_or(str::is_empty);
+        let startup_status = (!in_memory)
+            .then(|| db::enable_wal(&connection).err())
+            .flatten()
+            .map(|err| {
+                Status::Error(format!(
+                    "WAL unavailable, using the default journal: {:#}",
+                    err
+                ))
+            });
+        db::init_schema(&connection).wrap_err("failed to initialize database")?;
+
+        let config = Config::load(&data_dir)?;
+        let keymap = KeyMap::load(&data_dir)?;
+        // The theme is shared by every list, so it lives in the database opened first.
+        let theme_name = db::read_setting(&connection, THEME_SETTING)?
+            .and_then(|label| ThemeName::from_label(&label))
+            .unwrap_or_default();
 
         let mut app = Self {
             should_exit: false,
             items: Vec::new(),
             state: ListState::default(),
             mode: Mode::Normal,
-            input_buffer: String::new(),
+            input_buffer: TextInput::default(),
+            search_buffer: TextInput::default(),
+            tag_filter: None,
+            marked: HashSet::new(),
+            show_archived: false,
+            show_detail: false,
+            hide_done: false,
+            completed_collapsed: false,
+            today_view: None,
+            priority_filter: None,
+            sort_mode: config.default_sort.unwrap_or_default(),
+            default_sort: config.default_sort.unwrap_or_default(),
+            default_filter: config.default_filter,
+            status: startup_status,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            session_toggled: HashSet::new(),
+            tag_stats: Vec::new(),
+            activity: Vec::new(),
+            activity_state: ListState::default(),
+            list_names: Vec::new(),
+            lists_state: ListState::default(),
+            home_path: connection
+                .path()
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            active_list: None,
+            streak: 0,
+            completions_per_day: Vec::new(),
+            chart_days: config.chart_days,
+            reminders: Vec::new(),
+            pending_count: None,
+            pending_g: false,
+            delete_count: 1,
+            editor_requested: false,
+            keymap,
+            max_description_length: config.max_description_length,
+            stale_after_days: config.stale_after_days,
+            line_numbers: config.line_numbers,
+            large_list_warning: config.large_list_warning,
+            load_window: config.load_window,
+            window: None,
+            duplicate_warning: None,
+            subtask_parent: None,
+            draft: String::new(),
+            tag_suggestions: Vec::new(),
+            suggestion: 0,
+            saved_at: None,
+            data_dir,
+            theme_name,
+            configured_theme: config.theme,
+            theme: config.theme,
+            list_area: Rect::default(),
+            list_offset: 0,
+            paged: false,
+            demo: false,
             connection,
         };
 
+        let saved_id: Option<i64> = db::read_setting(&app.connection, SELECTED_SETTING)?
+            .and_then(|value| value.parse().ok());
+        // Leaving the view goes back to the saved task, as if it had been toggled on.
+        if config.default_filter == Some(StartupFilter::Today) {
+            app.today_view = Some(saved_id);
+        }
+        app.apply_theme();
+        app.load_view_settings()?;
         app.load_tasks()?;
-
-        if !app.items.is_empty() {
-            app.state.select_first();
+        // A saved task hidden by the filter leaves the first visible row selected.
+        app.reset_selection();
+        if let Some(id) = saved_id {
+            app.select_id(id);
+        }
+        if config.reminders {
+            let now = Local::now().naive_local();
+            app.reminders = app
+                .items
+                .iter()
+                .filter(|item| {
+                    !item.is_done && item.due_date.is_some_and(|due| due <= now.date())
+                        || item.is_due_within(now, REMINDER_WINDOW)
+                })
+                .cloned()
+                .collect();
+            // Appointments coming up within the hour are listed before everything else.
+            app.reminders.sort_by_key(|item| {
+                (
+                    !item.is_due_within(now, REMINDER_WINDOW),
+                    item.due_date,
+                    item.due_time,
+                )
+            });
         }
 
         Ok(app)
     }
 
+    /// Marks the app as running on throwaway demo data, shown in the title and footer.
+    pub fn demo(mut self) -> Self {
+        self.demo = true;
+        self
+    }
+
+    /// Whether this is a demo, which never writes to the data directory, saying so in the
+    /// footer when it is.
+    fn refuse_in_demo(&mut self) -> bool {
+        if self.demo {
+            self.status = Some(Status::Info(
+                "Demo mode, nothing is saved to disk".to_string(),
+            ));
+        }
+        self.demo
+    }
+
     fn load_tasks(&mut self) -> Result<()> {
-        let mut stmt = self
-            .connection
-            .prepare("SELECT id, description, is_done FROM tasks ORDER BY id")?;
+        if self.windowed() {
+            return self.load_window_at(self.window.map_or(0, |window| window.start));
+        }
+        self.window = None;
+        self.items = db::load_tasks(&self.connection, Some(self.show_archived), self.sort_mode)?;
+        Ok(())
+    }
 
-        let task_iter = stmt.query_map([], |row| {
-            Ok(TodoItem {
-                id: row.get(0)?,
-                description: row.get(1)?,
-                is_done: row.get::<_, i32>(2)? != 0,
-            })
+    /// Whether only a window of the list should be loaded: `load_window` is set and no search
+    /// or filter needs every task in memory.
+    fn windowed(&self) -> bool {
+        self.load_window > 0
+            && self.search_buffer.is_empty()
+            && self.tag_filter.is_none()
+            && !self.hide_done
+            && !self.completed_collapsed
+            && self.priority_filter.is_none()
+            && self.today_view.is_none()
+    }
+
+    /// Loads the window starting `start` tasks into the list, moved back so it doesn't run
+    /// past the end.
+    fn load_window_at(&mut self, start: usize) -> Result<()> {
+        let counts = self.count_tasks()?;
+        let start = start.min(counts.total.saturating_sub(self.load_window));
+        self.items = db::load_task_window(
+            &self.connection,
+            self.show_archived,
+            self.sort_mode,
+            start,
+            self.load_window,
+        )?;
+        self.window = Some(TaskWindow { start, counts });
+        Ok(())
+    }
+
+    fn count_tasks(&self) -> Result<TaskCounts> {
+        let stale_before = (self.stale_after_days > 0)
+            .then(|| Utc::now() - TimeDelta::days(self.stale_after_days.into()));
+        db::task_counts(&self.connection, self.show_archived, stale_before)
+    }
+
+    /// Recounts the full list after a change that didn't reload the window.
+    fn refresh_counts(&mut self) -> Result<()> {
+        if self.window.is_some() {
+            let counts = self.count_tasks()?;
+            if let Some(window) = &mut self.window {
+                window.counts = counts;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reloads after a search or filter starts or stops needing every task in memory.
+    fn sync_window(&mut self) {
+        if self.windowed() != self.window.is_some()
+            && let Err(err) = self.load_tasks()
+        {
+            self.report_error(err);
+        }
+    }
+
+    /// Makes sure the task `position` tasks into the full list is loaded, moving the window
+    /// to center on it if not, and returns its row. Without a window positions are rows.
+    fn load_position(&mut self, position: usize) -> Option<usize> {
+        let Some(window) = self.window else {
+            return Some(position);
+        };
+        if position >= window.counts.total {
+            return None;
+        }
+        if !(window.start..window.start + self.items.len()).contains(&position)
+            && let Err(err) = self.load_window_at(position.saturating_sub(self.load_window / 2))
+        {
+            self.report_error(err);
+            return None;
+        }
+        Some(position - self.window.map_or(0, |window| window.start))
+    }
+
+    /// Selects the task `position` tasks into the full list, or the last one when there
+    /// aren't that many, loading it first if needed.
+    fn select_position(&mut self, position: usize) {
+        let last = self
+            .window
+            .map(|window| window.counts.total.saturating_sub(1));
+        if let Some(row) = self.load_position(last.map_or(position, |last| position.min(last))) {
+            self.select_row(row);
+        }
+    }
+
+    /// The selection counted from the top of the full list rather than the window.
+    fn selected_position(&self) -> Option<usize> {
+        let row = self.state.selected()?;
+        Some(row + self.window.map_or(0, |window| window.start))
+    }
+
+    fn add_task(&mut self, input: &str) -> Result<i64> {
+        self.insert_task(input, None)
+    }
+
+    fn add_subtask(&mut self, parent_id: i64, input: &str) -> Result<i64> {
+        self.insert_task(input, Some(parent_id))
+    }
+
+    /// Adds a task from typed input, as a subtask of `parent_id` when given. A subtask
+    /// without a `project:` prefix joins its parent's project.
+    fn insert_task(&mut self, input: &str, parent_id: Option<i64>) -> Result<i64> {
+        let (category, input) = parse_category(input);
+        let category = category.or_else(|| {
+            let parent_id = parent_id?;
+            self.items
+                .iter()
+                .find(|item| item.id == parent_id)
+                .and_then(|parent| parent.category.clone())
+        });
+        let (description, tags) = parse_tags(input);
+        let now = Utc::now();
+        let id = with_transaction(&mut self.connection, |tx| {
+            db::add_task(tx, &description, &tags, category.as_deref(), parent_id, now)
         })?;
+        self.record(Action::Added(id));
+        self.load_tasks()?;
+
+        Ok(id)
+    }
 
-        self.items.clear();
-        for task in task_iter {
-            self.items.push(task?);
+    /// Copies the selected description to the clipboard, or to a file in the data directory
+    /// without one.
+    fn yank_selected(&mut self) {
+        if self.refuse_in_demo() {
+            return;
         }
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        self.status = Some(match clipboard::copy(&item.description, &self.data_dir) {
+            Ok(Copied::Terminal) => Status::Info("Sent to terminal clipboard".to_string()),
+            Ok(Copied::File(path)) => {
+                Status::Info(format!("No clipboard, copied to {}", path.display()))
+            }
+            Err(err) => Status::Error(format!("Copy failed: {:#}", err)),
+        });
+    }
 
+    /// Opens the first link in the selected task's description in the default browser.
+    fn open_selected_url(&mut self) {
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let Some(url) = browser::find_url(&item.description) else {
+            self.status = Some(Status::Error("No link in this task".to_string()));
+            return;
+        };
+        self.status = Some(match browser::open(url) {
+            Ok(()) => Status::Info(format!("Opened {}", url)),
+            Err(err) => Status::Error(format!("Couldn't open {}: {:#}", url, err)),
+        });
+    }
+
+    /// Copies the selected task, tags and notes included, into a new incomplete task just
+    /// below it and selects the copy.
+    fn duplicate_selected(&mut self) -> Result<()> {
+        let Some(item) = self.selected_item().cloned() else {
+            return Ok(());
+        };
+        let id = with_transaction(&mut self.connection, |tx| {
+            db::duplicate_task(tx, &item, Utc::now())
+        })?;
+        self.record(Action::Added(id));
+        self.load_tasks()?;
+        self.select_id(id);
+        self.status = Some(Status::Info("Task duplicated".to_string()));
         Ok(())
     }
 
-    fn add_task(&mut self, description: &str) -> Result<()> {
-        self.connection.execute(
-            "INSERT INTO tasks (description, is_done) VALUES (?1, 0)",
-            [description],
-        )?;
+    /// Inserts `tasks` in a single transaction and returns how many rows were added.
+    fn import_tasks(&mut self, tasks: &[ImportedTask], mode: ImportMode) -> Result<usize> {
+        let imported =
+            with_transaction(&mut self.connection, |tx| db::import_tasks(tx, tasks, mode))?;
 
-        let id = self.connection.last_insert_rowid();
-        self.items.push(TodoItem::new(id, description));
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.load_tasks()?;
+        Ok(imported)
+    }
+
+    fn toggle_task(&mut self, index: usize) -> Result<()> {
+        if let Some(item) = self
+            .items
+            .get(index)
+            .filter(|item| !item.is_done && item.recurrence != Recurrence::None)
+            .cloned()
+        {
+            let now = Utc::now();
+            let next_id = with_transaction(&mut self.connection, |tx| {
+                db::log_activity(tx, Activity::Completed, &item.description, now)?;
+                db::complete_occurrence(tx, &item, now)
+            })?;
+            self.record(Action::Recurred {
+                id: item.id,
+                next_id,
+            });
+            self.load_tasks()?;
+            self.clamp_selection();
+        } else if let Some(item) = self.items.get_mut(index) {
+            let previous = item.status;
+            let now = Utc::now();
+            let completed_at = with_transaction(&mut self.connection, |tx| {
+                let activity = if item.is_done {
+                    Activity::Reopened
+                } else {
+                    Activity::Completed
+                };
+                db::log_activity(tx, activity, &item.description, now)?;
+                db::set_done(tx, item.id, !item.is_done, now)
+            })?;
+            item.is_done = !item.is_done;
+            item.updated_at = Some(now);
+            item.status = if item.is_done {
+                TaskStatus::Done
+            } else {
+                TaskStatus::Todo
+            };
+            item.completed_at = completed_at;
+            let id = item.id;
+            // Undoing a plain toggle would lose "doing", so remember it.
+            self.record(if previous == TaskStatus::Doing {
+                Action::StatusChanged { id, previous }
+            } else {
+                Action::Toggled(id)
+            });
+        }
+        self.regroup_done()
+    }
 
+    /// Unblocks or blocks again the tasks waiting on ones whose completion changed, then
+    /// reloads the list in the done-last sort so those tasks move to their group, with the
+    /// selection following the selected task rather than its row.
+    fn regroup_done(&mut self) -> Result<()> {
+        self.resolve_blocked();
+        if self.sort_mode == SortMode::DoneLast {
+            let selected_id = self.selected_item().map(|item| item.id);
+            self.load_tasks()?;
+            if let Some(id) = selected_id {
+                self.select_id(id);
+            }
+        }
         Ok(())
     }
 
-    fn toggle_task(&mut self, index: usize) -> Result<()> {
+    /// Recomputes `blocked` from the loaded tasks after completion changed in place. Tasks
+    /// whose blocker isn't loaded keep what the database said.
+    fn resolve_blocked(&mut self) {
+        let done: HashMap<i64, bool> = self
+            .items
+            .iter()
+            .map(|item| (item.id, item.is_done))
+            .collect();
+        for item in &mut self.items {
+            if let Some(done) = item.blocked_by.and_then(|blocker| done.get(&blocker)) {
+                item.blocked = !done;
+            }
+        }
+    }
+
+    /// The footer warning for trying to finish a task that is still blocked.
+    fn blocked_warning(&self, item: &TodoItem) -> Status {
+        Status::Error(
+            match item
+                .blocked_by
+                .and_then(|blocker| self.items.iter().find(|other| other.id == blocker))
+            {
+                Some(blocker) => format!("Blocked until \"{}\" is done", blocker.description),
+                None => "Blocked until another task is done".to_string(),
+            },
+        )
+    }
+
+    fn update_task(&mut self, index: usize, input: &str) -> Result<()> {
         if let Some(item) = self.items.get_mut(index) {
-            item.is_done = !item.is_done;
-            self.connection.execute(
-                "UPDATE tasks SET is_done = ?1 WHERE id = ?2",
-                rusqlite::params![item.is_done as i32, item.id],
-            )?;
+            let (category, input) = parse_category(input);
+            let (description, tags) = parse_tags(input);
+            if description == item.description && tags == item.tags && category == item.category {
+                return Ok(());
+            }
+            let now = Utc::now();
+            with_transaction(&mut self.connection, |tx| {
+                db::update_task(tx, item.id, &description, &tags, category.as_deref(), now)
+            })?;
+
+            item.updated_at = Some(now);
+            let id = item.id;
+            let old_description = std::mem::replace(&mut item.description, description);
+            let old_tags = std::mem::replace(&mut item.tags, tags);
+            let recategorized = item.category != category;
+            let old_category = std::mem::replace(&mut item.category, category);
+            self.record(Action::Edited {
+                id,
+                old_description,
+                old_tags,
+                old_category,
+            });
+            if recategorized {
+                // A new category moves the task to another group.
+                self.load_tasks()?;
+                self.select_id(id);
+            }
         }
         Ok(())
     }
 
-    fn delete_task(&mut self, index: usize) -> Result<()> {
-        if index < self.items.len() {
-            let id = self.items[index].id;
-            self.connection
-                .execute("DELETE FROM tasks WHERE id = ?1", [id])?;
-            self.items.remove(index);
+    fn set_status(&mut self, index: usize, status: TaskStatus) -> Result<()> {
+        let Some(item) = self.items.get(index) else {
+            return Ok(());
+        };
+        if status == TaskStatus::Done && item.recurrence != Recurrence::None {
+            // Completing a recurring task goes through toggling to schedule the next one.
+            return self.toggle_task(index);
+        }
+        let (id, previous) = (item.id, item.status);
+        let now = Utc::now();
+        let completed_at = db::set_status(&self.connection, id, status, now)?;
+        let item = &mut self.items[index];
+        item.updated_at = Some(now);
+        item.status = status;
+        item.is_done = status == TaskStatus::Done;
+        item.completed_at = completed_at;
+        self.record(Action::StatusChanged { id, previous });
+        self.regroup_done()
+    }
+
+    fn set_priority(&mut self, index: usize, priority: Priority) -> Result<()> {
+        if let Some(item) = self.items.get_mut(index) {
+            let now = Utc::now();
+            db::set_priority(&self.connection, item.id, priority, now)?;
+            item.priority = priority;
+            item.updated_at = Some(now);
         }
         Ok(())
     }
 
-    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
-        while !self.should_exit {
-            terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
+    fn set_recurrence(&mut self, index: usize, recurrence: Recurrence) -> Result<()> {
+        if let Some(item) = self.items.get_mut(index) {
+            let now = Utc::now();
+            db::set_recurrence(&self.connection, item.id, recurrence, now)?;
+            item.recurrence = recurrence;
+            item.updated_at = Some(now);
+        }
+        Ok(())
+    }
+
+    fn set_color(&mut self, index: usize, color: TaskColor) -> Result<()> {
+        if let Some(item) = self.items.get_mut(index) {
+            let now = Utc::now();
+            db::set_color(&self.connection, item.id, color, now)?;
+            item.color = color;
+            item.updated_at = Some(now);
+        }
+        Ok(())
+    }
 
-            if let Event::Key(key) = event::read()? {
-                self.handle_event(key)?;
+    fn set_notes(&mut self, index: usize, notes: String) -> Result<()> {
+        if let Some(item) = self.items.get_mut(index) {
+            if notes == item.notes {
+                return Ok(());
             }
+            let now = Utc::now();
+            db::set_notes(&self.connection, item.id, &notes, now)?;
+            let id = item.id;
+            let old_notes = std::mem::replace(&mut item.notes, notes);
+            item.updated_at = Some(now);
+            self.record(Action::NotesEdited { id, old_notes });
         }
+        Ok(())
+    }
 
+    fn set_due_date(
+        &mut self,
+        index: usize,
+        due_date: Option<NaiveDate>,
+        due_time: Option<NaiveTime>,
+    ) -> Result<()> {
+        if let Some(item) = self.items.get_mut(index) {
+            let now = Utc::now();
+            db::set_due_date(&self.connection, item.id, due_date, due_time, now)?;
+            item.due_date = due_date;
+            item.due_time = due_time;
+            item.updated_at = Some(now);
+        }
         Ok(())
     }
 
-    fn handle_event(&mut self, key: event::KeyEvent) -> Result<()> {
-        if key.kind != KeyEventKind::Press {
-            return Ok(());
+    fn delete_task(&mut self, index: usize) -> Result<()> {
+        if let Some(item) = self.items.get(index) {
+            with_transaction(&mut self.connection, |tx| {
+                db::log_activity(tx, Activity::Deleted, &item.description, Utc::now())?;
+                db::delete_task(tx, item.id)
+            })?;
+            let item = self.items.remove(index);
+            self.record(Action::Deleted(item));
         }
+        Ok(())
+    }
 
-        match self.mode {
-            Mode::Normal => match key.code {
-                KeyCode::Char('q') => self.should_exit = true,
-                KeyCode::Char('a') => {
-                    self.mode = Mode::Input;
-                    self.input_buffer.clear();
-                }
-                KeyCode::Char('j') | KeyCode::Down => self.select_next(),
-                KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
-                KeyCode::Char('g') => self.select_first(),
-                KeyCode::Char('G') => self.select_last(),
-                KeyCode::Char(' ') | KeyCode::Enter => self.toggle_selected()?,
-                KeyCode::Char('d') => self.delete_selected()?,
-                _ => {}
-            },
-            Mode::Input => match key.code {
-                KeyCode::Enter => {
-                    if !self.input_buffer.is_empty() {
-                        self.add_task(&self.input_buffer.clone())?;
-                        self.input_buffer.clear();
-                        self.state.select_last();
-                    }
-                    self.mode = Mode::Normal;
-                }
-                KeyCode::Esc => {
-                    self.input_buffer.clear();
-                    self.mode = Mode::Normal;
-                }
-                KeyCode::Backspace => {
-                    self.input_buffer.pop();
-                }
-                KeyCode::Char(c) => {
-                    self.input_buffer.push(c);
+    /// Flips completion for every task in `ids` within a single transaction.
+    fn toggle_tasks(&mut self, ids: &HashSet<i64>) -> Result<()> {
+        let now = Utc::now();
+        let items = &self.items;
+        let (toggled, recurred) = with_transaction(&mut self.connection, |tx| {
+            let mut toggled = Vec::new();
+            let mut recurred = Vec::new();
+            for item in items.iter().filter(|item| ids.contains(&item.id)) {
+                let activity = if item.is_done {
+                    Activity::Reopened
+                } else {
+                    Activity::Completed
+                };
+                db::log_activity(tx, activity, &item.description, now)?;
+                if !item.is_done && item.recurrence != Recurrence::None {
+                    recurred.push((item.id, db::complete_occurrence(tx, item, now)?));
+                } else {
+                    toggled.push((item.id, db::set_done(tx, item.id, !item.is_done, now)?));
                 }
-                _ => {}
-            },
+            }
+            Ok((toggled, recurred))
+        })?;
+
+        for (id, completed_at) in toggled {
+            if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+                item.is_done = completed_at.is_some();
+                item.updated_at = Some(now);
+                item.status = if item.is_done {
+                    TaskStatus::Done
+                } else {
+                    TaskStatus::Todo
+                };
+                item.completed_at = completed_at;
+            }
+            self.record(Action::Toggled(id));
+        }
+        if !recurred.is_empty() {
+            for (id, next_id) in recurred {
+                self.record(Action::Recurred { id, next_id });
+            }
+            self.load_tasks()?;
+            self.clamp_selection();
+        }
+        self.regroup_done()
+    }
+
+    /// Marks every visible task done, or every one undone when they are all done already.
+    fn toggle_all(&mut self) -> Result<()> {
+        // Every task is visible in a windowed list, including the ones not loaded.
+        let all;
+        let visible: Vec<&TodoItem> = if self.window.is_some() {
+            all = db::load_tasks(&self.connection, Some(self.show_archived), self.sort_mode)?;
+            all.iter().collect()
+        } else {
+            self.visible_indices()
+                .into_iter()
+                .map(|index| &self.items[index])
+                .collect()
+        };
+        if visible.is_empty() {
+            return Ok(());
+        }
+        let done = visible.iter().any(|item| !item.is_done);
+        let blocked = visible
+            .iter()
+            .filter(|item| done && !item.is_done && item.blocked)
+            .count();
+        let changed: Vec<(i64, String)> = visible
+            .into_iter()
+            .filter(|item| item.is_done != done && !(done && item.blocked))
+            .map(|item| (item.id, item.description.clone()))
+            .collect();
+        let ids: Vec<i64> = changed.iter().map(|(id, _)| *id).collect();
+
+        let activity = if done {
+            Activity::Completed
+        } else {
+            Activity::Reopened
+        };
+        let now = Utc::now();
+        with_transaction(&mut self.connection, |tx| {
+            for (_, description) in &changed {
+                db::log_activity(tx, activity, description, now)?;
+            }
+            db::set_done_many(tx, &ids, done, now)
+        })?;
+        for &id in &ids {
+            self.record(Action::Toggled(id));
         }
 
+        let selected_id = self.selected_item().map(|item| item.id);
+        self.load_tasks()?;
+        if let Some(id) = selected_id {
+            self.select_id(id);
+        }
+        self.clamp_selection();
+        self.status = Some(Status::Info(format!(
+            "Marked {} tasks {}{}",
+            ids.len(),
+            if done { "done" } else { "undone" },
+            match blocked {
+                0 => String::new(),
+                blocked => format!(", {} still blocked", blocked),
+            }
+        )));
         Ok(())
     }
 
-    fn toggle_selected(&mut self) -> Result<()> {
-        if let Some(index) = self.state.selected() {
-            self.toggle_task(index)?;
+    /// Deletes every task in `ids` within a single transaction.
+    fn delete_tasks(&mut self, ids: &HashSet<i64>) -> Result<()> {
+        let now = Utc::now();
+        let items = &self.items;
+        with_transaction(&mut self.connection, |tx| {
+            for item in items.iter().filter(|item| ids.contains(&item.id)) {
+                db::log_activity(tx, Activity::Deleted, &item.description, now)?;
+                db::delete_task(tx, item.id)?;
+            }
+            Ok(())
+        })?;
+
+        let (deleted, kept) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition(|item| ids.contains(&item.id));
+        self.items = kept;
+        for item in deleted {
+            self.record(Action::Deleted(item));
         }
         Ok(())
     }
 
-    fn delete_selected(&mut self) -> Result<()> {
-        if let Some(index) = self.state.selected() {
-            self.delete_task(index)?;
-            if self.items.is_empty() {
-                self.state.select(None);
-            } else if index >= self.items.len() {
-                self.state.select_last();
+    /// Deletes every completed task in the current view with a single statement and returns
+    /// how many were removed. Each one is recorded so undo can bring them back, including
+    /// those a window or filter keeps out of `items`.
+    fn delete_completed(&mut self) -> Result<usize> {
+        let (now, archived) = (Utc::now(), self.show_archived);
+        let deleted = with_transaction(&mut self.connection, |tx| {
+            let deleted = db::completed_tasks(tx, archived)?;
+            for item in &deleted {
+                db::log_activity(tx, Activity::Deleted, &item.description, now)?;
             }
+            db::delete_completed(tx, archived)?;
+            Ok(deleted)
+        })?;
+
+        let removed = deleted.len();
+        for item in deleted {
+            self.record(Action::Deleted(item));
+        }
+        self.load_tasks()?;
+        Ok(removed)
+    }
+
+    /// Moves the task into or out of the archive; it leaves the current view either way.
+    fn set_archived(&mut self, index: usize, archived: bool) -> Result<()> {
+        if index < self.items.len() {
+            db::set_archived(&self.connection, self.items[index].id, archived, Utc::now())?;
+            self.items.remove(index);
         }
         Ok(())
     }
 
-    fn select_next(&mut self) {
-        self.state.select_next();
+    fn swap_positions(&mut self, index: usize, other: usize) -> Result<()> {
+        if index >= self.items.len() || other >= self.items.len() {
+            return Ok(());
+        }
+
+        let (first, second) = (&self.items[index], &self.items[other]);
+        with_transaction(&mut self.connection, |tx| {
+            db::set_position(tx, first.id, second.position)?;
+            db::set_position(tx, second.id, first.position)
+        })?;
+
+        let position = self.items[index].position;
+        self.items[index].position = self.items[other].position;
+        self.items[other].position = position;
+        self.items.swap(index, other);
+        Ok(())
     }
 
-    fn select_previous(&mut self) {
-        self.state.select_previous();
+    /// Pushes a new change onto the undo stack, dropping whatever could have been redone.
+    fn record(&mut self, action: Action) {
+        self.redo_stack.clear();
+        self.push_undo(action);
     }
 
-    fn select_first(&mut self) {
-        self.state.select_first();
+    fn push_undo(&mut self, action: Action) {
+        if let Action::Toggled(id)
+        | Action::StatusChanged { id, .. }
+        | Action::Recurred { id, .. } = action
+        {
+            self.session_toggled.insert(id);
+        }
+        if self.undo_stack.len() >= UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(action);
     }
 
-    fn select_last(&mut self) {
-        self.state.select_last();
+    fn undo(&mut self) -> Result<()> {
+        let Some(action) = self.undo_stack.pop() else {
+            self.status = Some(Status::Error("Nothing to undo".to_string()));
+            return Ok(());
+        };
+
+        let label = action.label();
+        let (id, inverse) = self.revert(action)?;
+        self.redo_stack.push((inverse, label));
+        self.after_revert(id, format!("Undid {}", label))
     }
-}
 
-impl Widget for &mut App {
-    fn render(self, area: Rect, buf: &mut Buffer) {
-        let [main_area, footer_area] =
-            Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(area);
+    fn redo(&mut self) -> Result<()> {
+        let Some((action, label)) = self.redo_stack.pop() else {
+            self.status = Some(Status::Error("Nothing to redo".to_string()));
+            return Ok(());
+        };
 
-        self.render_list(main_area, buf);
-        self.render_footer(footer_area, buf);
+        let (id, inverse) = self.revert(action)?;
+        self.push_undo(inverse);
+        self.after_revert(id, format!("Redid {}", label))
     }
-}
 
-impl App {
-    fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
-        let block = Block::default()
-            .title(" Task Manager ")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan));
+    fn after_revert(&mut self, id: Option<i64>, message: String) -> Result<()> {
+        self.load_tasks()?;
+        if let Some(id) = id {
+            self.select_id(id);
+        }
+        self.clamp_selection();
+        self.status = Some(Status::Info(message));
+        Ok(())
+    }
 
-        let items: Vec<ListItem> = self
-            .items
-            .iter()
-            .map(|item| {
-                let checkbox = if item.is_done { "[x]" } else { "[ ]" };
-                let style = if item.is_done {
-                    Style::default().fg(Color::DarkGray).crossed_out()
-                } else {
-                    Style::default().fg(Color::White)
+    /// Reverses `action` in the database, returning the task to select and the action that
+    /// reverses this in turn, so undo and redo can hand changes back and forth.
+    fn revert(&mut self, action: Action) -> Result<(Option<i64>, Action)> {
+        let now = Utc::now();
+        Ok(match action {
+            Action::Added(id) => {
+                let item = self.stored_item(id)?;
+                db::delete_task(&self.connection, id)?;
+                match item {
+                    Some(item) => (None, Action::Deleted(item)),
+                    None => (None, Action::Added(id)),
+                }
+            }
+            Action::Deleted(item) => {
+                with_transaction(&mut self.connection, |tx| db::restore_task(tx, &item))?;
+                (Some(item.id), Action::Added(item.id))
+            }
+            Action::Toggled(id) => {
+                db::flip_done(&self.connection, id, now)?;
+                (Some(id), Action::Toggled(id))
+            }
+            Action::Edited {
+                id,
+                old_description,
+                old_tags,
+                old_category,
+            } => {
+                let current = self.stored_item(id)?;
+                with_transaction(&mut self.connection, |tx| {
+                    db::update_task(
+                        tx,
+                        id,
+                        &old_description,
+                        &old_tags,
+                        old_category.as_deref(),
+                        now,
+                    )
+                })?;
+                let inverse = match current {
+                    Some(item) => Action::Edited {
+                        id,
+                        old_description: item.description,
+                        old_tags: item.tags,
+                        old_category: item.category,
+                    },
+                    None => Action::Edited {
+                        id,
+                        old_description,
+                        old_tags,
+                        old_category,
+                    },
                 };
-                ListItem::new(format!("{} {}", checkbox, item.description)).style(style)
-            })
-            .collect();
+                (Some(id), inverse)
+            }
+            Action::NotesEdited { id, old_notes } => {
+                let current = self.stored_item(id)?.map(|item| item.notes);
+                db::set_notes(&self.connection, id, &old_notes, now)?;
+                let old_notes = current.unwrap_or(old_notes);
+                (Some(id), Action::NotesEdited { id, old_notes })
+            }
+            Action::StatusChanged { id, previous } => {
+                let current = self.stored_item(id)?.map(|item| item.status);
+                db::set_status(&self.connection, id, previous, now)?;
+                let previous = current.unwrap_or(previous);
+                (Some(id), Action::StatusChanged { id, previous })
+            }
+            Action::Recurred { id, next_id } => {
+                with_transaction(&mut self.connection, |tx| {
+                    db::undo_occurrence(tx, id, next_id)
+                })?;
+                (Some(id), Action::Completed(id))
+            }
+            Action::Completed(id) => {
+                // Completing the reopened occurrence again spawns a fresh next one.
+                let Some(item) = self.stored_item(id)? else {
+                    return Ok((None, Action::Completed(id)));
+                };
+                let next_id = with_transaction(&mut self.connection, |tx| {
+                    db::complete_occurrence(tx, &item, now)
+                })?;
+                (Some(id), Action::Recurred { id, next_id })
+            }
+        })
+    }
+
+    /// Reads task `id` from the database, in or out of the archive.
+    fn stored_item(&self, id: i64) -> Result<Option<TodoItem>> {
+        Ok(db::load_tasks(&self.connection, None, SortMode::Id)?
+            .into_iter()
+            .find(|item| item.id == id))
+    }
+
+    /// Runs until the user quits, returning the tasks completed during the session.
+    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<Vec<TodoItem>> {
+        if !self.demo {
+            self.offer_draft();
+        }
+        let mut title_count = None;
+        let mut changes = (self.active_list.clone(), self.connection.total_changes());
+        while !self.should_exit {
+            if (&self.active_list, self.connection.total_changes()) != (&changes.0, changes.1) {
+                // Opening another list swaps the connection without writing anything.
+                if self.active_list == changes.0 {
+                    self.saved_at = Some(Instant::now());
+                }
+                changes = (self.active_list.clone(), self.connection.total_changes());
+                if let Err(err) = self.refresh_counts() {
+                    self.report_error(err);
+                }
+            }
+            if let Err(err) = self.update_title(&mut title_count) {
+                self.status = Some(Status::Error(format!("Title not updated: {:#}", err)));
+            }
+            terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
+
+            // Wake up to redraw, and to hide the saved indicator on time, even if no key is
+            // pressed.
+            let mut timeout = TICK;
+            if let Some(saved_at) = self.saved_at {
+                timeout = timeout.min(SAVED_INDICATOR.saturating_sub(saved_at.elapsed()));
+            }
+            if !crossterm::event::poll(timeout)? {
+                if self
+                    .saved_at
+                    .is_some_and(|saved_at| saved_at.elapsed() >= SAVED_INDICATOR)
+                {
+                    self.saved_at = None;
+                }
+                continue;
+            }
+            let result = match crossterm::event::read()? {
+                Event::Key(key) => self.handle_event(key),
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                Event::Paste(text) => self.handle_paste(&text),
+                // Redraw at the new size straight away rather than on the next key.
+                Event::Resize(..) => terminal.autoresize().map_err(Into::into),
+                _ => Ok(()),
+            };
+            if let Err(err) = result {
+                self.report_error(err);
+            }
+            if std::mem::take(&mut self.editor_requested)
+                && let Err(err) = self.edit_notes_in_editor(&mut terminal)
+            {
+                self.report_error(err);
+            }
+            if !self.demo
+                && let Err(err) = self.save_draft()
+            {
+                self.status = Some(Status::Error(format!("Draft not saved: {:#}", err)));
+            }
+        }
 
-        let list = List::new(items)
-            .block(block)
-            .highlight_symbol("▶ ")
-            .highlight_style(Style::default().fg(Color::Yellow).bold());
+        self.save_selection()?;
+        self.session_completions()
+    }
+
+    /// Shows the open task count in the terminal title when it differs from `shown`, the
+    /// count last written there. `shown` is left alone when either step fails.
+    fn update_title(&self, shown: &mut Option<i64>) -> Result<()> {
+        let remaining = db::remaining_count(&self.connection)?;
+        if *shown != Some(remaining) {
+            execute!(
+                io::stdout(),
+                SetTitle(format!(
+                    "{}Tasks ({} left)",
+                    if self.demo { "[DEMO] " } else { "" },
+                    remaining
+                ))
+            )?;
+            *shown = Some(remaining);
+        }
+        Ok(())
+    }
 
-        StatefulWidget::render(list, area, buf, &mut self.state);
+    /// The tasks completed since launch that are still done, in list order.
+    fn session_completions(&self) -> Result<Vec<TodoItem>> {
+        let mut items = db::load_tasks(&self.connection, None, self.sort_mode)?;
+        items.retain(|item| item.is_done && self.session_toggled.contains(&item.id));
+        Ok(items)
     }
 
-    fn render_footer(&mut self, area: Rect, buf: &mut Buffer) {
-        let text = match self.mode {
-            Mode::Normal => " q: Quit | a: Add | j/k: Navigate | Enter/Space: Toggle | d: Delete ",
-            Mode::Input => " Type task description, Enter to save, Esc to cancel ",
-        };
-
-        let footer = if self.mode == Mode::Input {
-            let input_text = format!(" New task: {}▏", self.input_buffer);
-            Paragraph::new(input_text)
-                .style(Style::default().fg(Color::Yellow))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::Yellow))
-                        .title(" Input Mode "),
-                )
-        } else {
-            Paragraph::new(text)
-                .style(Style::default().fg(Color::DarkGray))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .border_style(Style::default().fg(Color::DarkGray)),
-                )
-        };
+    /// Shows a failed action in the footer instead of exiting. In-memory state may have been
+    /// updated before the failing statement, so it is reloaded from the database.
+    fn report_error(&mut self, err: color_eyre::Report) {
+        self.mode = Mode::Normal;
+        if self.load_tasks().is_ok() {
+            self.clamp_selection();
+        }
+        self.status = Some(Status::Error(format!("Error: {:#}", err)));
+    }
+
+    /// Remembers the highlighted task so the next launch can select it again.
+    fn save_selection(&self) -> Result<()> {
+        match self.selected_item() {
+            Some(item) => {
+                db::write_setting(&self.connection, SELECTED_SETTING, &item.id.to_string())
+            }
+            None => db::delete_setting(&self.connection, SELECTED_SETTING),
+        }
+    }
+
+    fn submit_input(&mut self) -> Result<()> {
+        if self.mode == Mode::DueDate {
+            return self.submit_due_date();
+        }
+
+        let description = normalize_whitespace(self.input_buffer.as_str());
+        if description.is_empty() {
+            self.status = Some(Status::Error(
+                "Task description cannot be empty, Esc cancels".to_string(),
+            ));
+            return Ok(());
+        }
+        let length = description.chars().count();
+        if length > self.max_description_length {
+            self.status = Some(Status::Error(format!(
+                "Task description is {} characters, the limit is {}",
+                length, self.max_description_length
+            )));
+            return Ok(());
+        }
+
+        let warned = self.duplicate_warning.take();
+        if self.mode == Mode::Input
+            && warned.as_deref() != Some(description.as_str())
+            && db::has_incomplete_duplicate(
+                &self.connection,
+                &parse_tags(parse_category(&description).1).0,
+            )?
+        {
+            self.status = Some(Status::Error(
+                "An incomplete task with this description exists, Enter adds it anyway".to_string(),
+            ));
+            self.duplicate_warning = Some(description);
+            return Ok(());
+        }
+
+        match self.mode {
+            Mode::Edit => {
+                if let Some(index) = self.selected_index() {
+                    self.update_task(index, &description)?;
+                }
+            }
+            _ => {
+                let added = match self.subtask_parent {
+                    Some(parent_id) => self.add_subtask(parent_id, &description),
+                    None => self.add_task(&description),
+                };
+                match added {
+                    Ok(id) => {
+                        self.subtask_parent = None;
+                        self.select_id(id);
+                    }
+                    // Stay in the prompt with the text intact so the add can be retried.
+                    Err(err) => {
+                        self.duplicate_warning = warned;
+                        self.status = Some(Status::Error(format!("Error: {:#}", err)));
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        self.input_buffer.clear();
+        self.mode = Mode::Normal;
+        Ok(())
+    }
+
+    /// The `#tag` being typed before the cursor in the add or edit prompt.
+    fn partial_tag(&self) -> Option<&str> {
+        if !matches!(self.mode, Mode::Input | Mode::Edit) {
+            return None;
+        }
+        Some(self.input_buffer.word_before_cursor()).filter(|word| word.starts_with('#'))
+    }
+
+    /// Looks up the tags that complete the one being typed, keeping the highlight while the
+    /// suggestions stay the same.
+    fn update_tag_suggestions(&mut self) -> Result<()> {
+        let suggestions = match self.partial_tag() {
+            Some(partial) => {
+                db::tags_starting_with(&self.connection, &partial.to_lowercase(), MAX_SUGGESTIONS)?
+            }
+            None => Vec::new(),
+        };
+        if suggestions != self.tag_suggestions {
+            self.tag_suggestions = suggestions;
+            self.suggestion = 0;
+        }
+        Ok(())
+    }
+
+    /// Completes the tag being typed with the highlighted suggestion.
+    fn accept_tag_suggestion(&mut self) {
+        if let Some(tag) = self.tag_suggestions.get(self.suggestion) {
+            self.input_buffer.replace_word_before_cursor(tag);
+            self.input_buffer.insert(' ');
+        }
+    }
+
+    /// Asks to restore the description left in the draft file by a session that crashed
+    /// mid-typing.
+    fn offer_draft(&mut self) {
+        self.draft = fs::read_to_string(self.data_dir.join(DRAFT_FILE)).unwrap_or_default();
+        if !self.draft.trim().is_empty() && self.mode == Mode::Normal {
+            self.mode = Mode::ConfirmRestore;
+        }
+    }
+
+    /// Writes the description being typed into the add prompt to the draft file whenever it
+    /// changes, and removes the file once the prompt is saved or cancelled.
+    fn save_draft(&mut self) -> Result<()> {
+        let text = match self.mode {
+            Mode::Input => self.input_buffer.as_str(),
+            // The offered draft stays on disk until it is answered.
+            Mode::ConfirmRestore | Mode::ConfirmDiscard { editing: false, .. } => &self.draft,
+            _ => "",
+        };
+        if text == self.draft {
+            return Ok(());
+        }
+        let path = self.data_dir.join(DRAFT_FILE);
+        if text.is_empty() {
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(err)
+                        .wrap_err_with(|| format!("Failed to remove {}", path.display()));
+                }
+                _ => {}
+            }
+        } else {
+            fs::write(&path, text)
+                .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        }
+        self.draft = text.to_string();
+        Ok(())
+    }
+
+    /// Whether leaving the prompt would lose typing: any text when adding, or a change to
+    /// the description when editing.
+    fn has_unsaved_input(&self) -> bool {
+        match self.mode {
+            Mode::Input => !self.input_buffer.is_empty(),
+            Mode::Edit => self
+                .selected_item()
+                .is_none_or(|item| item.editable_text() != self.input_buffer.as_str()),
+            _ => false,
+        }
+    }
+
+    /// Leaves the prompt, or asks first when that would lose typing. With `quit` the app
+    /// exits too.
+    fn leave_input(&mut self, quit: bool) {
+        if self.has_unsaved_input() {
+            self.mode = Mode::ConfirmDiscard {
+                quit,
+                editing: self.mode == Mode::Edit,
+            };
+            return;
+        }
+        self.cancel_input();
+        self.should_exit = quit;
+    }
+
+    fn cancel_input(&mut self) {
+        self.input_buffer.clear();
+        self.duplicate_warning = None;
+        self.subtask_parent = None;
+        self.mode = Mode::Normal;
+    }
+
+    fn submit_due_date(&mut self) -> Result<()> {
+        let input = self.input_buffer.as_str().trim();
+        let due = if input.is_empty() {
+            None
+        } else {
+            match parse_due_input(input, Local::now().date_naive()) {
+                Some(due) => Some(due),
+                None => {
+                    self.status = Some(Status::Error(format!(
+                        "Invalid date '{}', expected YYYY-MM-DD, a phrase like tomorrow or fri, and an optional HH:MM",
+                        input
+                    )));
+                    return Ok(());
+                }
+            }
+        };
+
+        if let Some(index) = self.selected_index() {
+            let (due_date, due_time) = due.unzip();
+            self.set_due_date(index, due_date, due_time.flatten())?;
+            self.status = Some(Status::Info(match due {
+                Some((date, time)) => format!(
+                    "Due date set to {} {}",
+                    date.format("%a"),
+                    format_due(date, time)
+                ),
+                None => "Due date cleared".to_string(),
+            }));
+        }
+        self.input_buffer.clear();
+        self.mode = Mode::Normal;
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: Command) -> Result<()> {
+        match command {
+            Command::Write => self.flush()?,
+            Command::Quit => self.should_exit = true,
+            Command::WriteQuit => {
+                self.flush()?;
+                self.should_exit = true;
+            }
+            Command::Sort(sort_mode) => {
+                self.set_sort_mode(sort_mode)?;
+                self.status = Some(Status::Info(format!("Sorted by {}", sort_mode.label())));
+            }
+            Command::HideDone(hide_done) => {
+                if hide_done != self.hide_done {
+                    self.toggle_hide_done()?;
+                }
+            }
+            // A windowed list loads the numbered task first.
+            Command::Delete(row) => match self
+                .load_position(row - 1)
+                .and_then(|row| self.visible_indices().get(row).copied())
+            {
+                Some(index) => {
+                    self.delete_task(index)?;
+                    self.clamp_selection();
+                    self.status = Some(Status::Info(format!("Deleted task {}", row)));
+                }
+                None => self.status = Some(Status::Error(format!("No task number {}", row))),
+            },
+            Command::ClearCompleted => self.clear_completed()?,
+            Command::List(name) => self.switch_list(&name)?,
+            Command::Block(row) => self.set_selected_blocker(Some(row))?,
+            Command::Unblock => self.set_selected_blocker(None)?,
+            Command::Compact => self.compact_positions()?,
+        }
+        Ok(())
+    }
+
+    /// Makes the selected task wait on the task on `row` of the current view, counted from
+    /// one, or on nothing when `row` is `None`.
+    fn set_selected_blocker(&mut self, row: Option<usize>) -> Result<()> {
+        let Some(id) = self.selected_item().map(|item| item.id) else {
+            return Ok(());
+        };
+        let blocker = match row {
+            // A windowed list loads the numbered task first.
+            Some(row) => match self
+                .load_position(row - 1)
+                .and_then(|row| self.visible_indices().get(row).copied())
+            {
+                Some(index) => Some(self.items[index].id),
+                None => {
+                    self.status = Some(Status::Error(format!("No task number {}", row)));
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+        db::set_blocked_by(&self.connection, id, blocker, Utc::now())?;
+        self.load_tasks()?;
+        self.select_id(id);
+        self.status = Some(Status::Info(match row {
+            Some(row) => format!("Blocked until task {} is done", row),
+            None => "No longer blocked".to_string(),
+        }));
+        Ok(())
+    }
+
+    /// Closes the gaps deletes leave in task positions. The order on screen stays the same.
+    fn compact_positions(&mut self) -> Result<()> {
+        let moved = with_transaction(&mut self.connection, |tx| db::renumber_positions(tx))?;
+        let selected_id = self.selected_item().map(|item| item.id);
+        self.load_tasks()?;
+        if let Some(id) = selected_id {
+            self.select_id(id);
+        }
+        self.status = Some(Status::Info(match moved {
+            0 => "Positions are already compact".to_string(),
+            moved => format!("Renumbered {} tasks", moved),
+        }));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.refuse_in_demo() {
+            return Ok(());
+        }
+        db::checkpoint(&self.connection)?;
+        self.status = Some(Status::Info("Saved and checkpointed to disk".to_string()));
+        Ok(())
+    }
+
+    fn submit_notes(&mut self) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let notes = self.input_buffer.take().trim_end().to_string();
+            self.set_notes(index, notes)?;
+            self.status = Some(Status::Info("Notes saved".to_string()));
+        }
+        self.mode = Mode::Normal;
+        Ok(())
+    }
+
+    fn edit_selected_notes(&mut self) {
+        if let Some(item) = self.selected_item() {
+            let notes = item.notes.clone();
+            self.input_buffer.set(notes);
+            self.mode = Mode::Notes;
+        }
+    }
+
+    fn request_editor(&mut self) {
+        if self.selected_index().is_none() {
+            return;
+        }
+        if editor_command().is_some() {
+            self.editor_requested = true;
+        } else {
+            self.status = Some(Status::Error(
+                "$EDITOR is not set, use n to edit notes here".to_string(),
+            ));
+        }
+    }
+
+    /// Suspends the TUI while `$EDITOR` edits the selected task's notes in a temp file,
+    /// then saves whatever the editor left in it.
+    fn edit_notes_in_editor(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let (Some(index), Some(editor)) = (self.selected_index(), editor_command()) else {
+            return Ok(());
+        };
+        let item = &self.items[index];
+        let path = env::temp_dir().join(format!(
+            "task-manager-notes-{}-{}.md",
+            process::id(),
+            item.id
+        ));
+        fs::write(&path, &item.notes)
+            .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+
+        suspend_terminal()?;
+        let edited = run_editor(&editor, &path);
+        resume_terminal(terminal)?;
+        let notes = edited.and_then(|()| {
+            fs::read_to_string(&path).wrap_err_with(|| format!("failed to read {}", path.display()))
+        });
+        let _ = fs::remove_file(&path);
+
+        self.set_notes(index, notes?.trim_end().to_string())?;
+        self.status = Some(Status::Info("Notes saved".to_string()));
+        Ok(())
+    }
+
+    fn edit_selected_due_date(&mut self) {
+        if let Some(item) = self.selected_item() {
+            self.input_buffer.set(item.due_label().unwrap_or_default());
+            self.mode = Mode::DueDate;
+        }
+    }
+
+    /// Pushes the selected task's due date `days` later, keeping its time, or makes it due
+    /// tomorrow when it has none.
+    fn snooze_selected(&mut self, days: u64) -> Result<()> {
+        let Some(index) = self.selected_index() else {
+            return Ok(());
+        };
+        let item = &self.items[index];
+        let (date, time) = match item.due_date {
+            Some(date) => (date.checked_add_days(Days::new(days)), item.due_time),
+            None => (Local::now().date_naive().succ_opt(), None),
+        };
+        let Some(date) = date else {
+            return Ok(());
+        };
+        self.set_due_date(index, Some(date), time)?;
+        self.status = Some(Status::Info(format!(
+            "Snoozed until {}",
+            format_due(date, time)
+        )));
+        Ok(())
+    }
+
+    fn edit_selected(&mut self) {
+        if let Some(item) = self.selected_item() {
+            let text = item.editable_text();
+            self.input_buffer.set(text);
+            self.mode = Mode::Edit;
+        }
+    }
+
+    fn cycle_tag_filter(&mut self) -> Result<()> {
+        // A window only holds some of the tags.
+        let mut tags: Vec<String> = match self.window {
+            Some(_) => db::tag_names(&self.connection, self.show_archived)?,
+            None => self
+                .items
+                .iter()
+                .flat_map(|item| item.tags.iter().cloned())
+                .collect(),
+        };
+        tags.sort();
+        tags.dedup();
+
+        let next = match &self.tag_filter {
+            None => tags.first(),
+            Some(current) => tags.iter().skip_while(|tag| *tag != current).nth(1),
+        };
+        self.tag_filter = next.cloned();
+        db::write_setting(
+            &self.connection,
+            TAG_FILTER_SETTING,
+            self.tag_filter.as_deref().unwrap_or_default(),
+        )?;
+        self.sync_window();
+        self.reset_selection();
+        Ok(())
+    }
+
+    fn clear_search(&mut self) {
+        let selected_id = self.selected_item().map(|item| item.id);
+        self.search_buffer.clear();
+        self.sync_window();
+        match selected_id {
+            Some(id) => self.select_id(id),
+            None => self.reset_selection(),
+        }
+    }
+
+    fn toggle_selected(&mut self) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let item = &self.items[index];
+            if item.blocked && !item.is_done {
+                self.status = Some(self.blocked_warning(item));
+                return Ok(());
+            }
+            self.toggle_task(index)?;
+            self.clamp_selection();
+        }
+        Ok(())
+    }
+
+    fn change_selected_status(&mut self, change: fn(TaskStatus) -> TaskStatus) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let item = &self.items[index];
+            let status = change(item.status);
+            if item.blocked && status == TaskStatus::Done {
+                self.status = Some(self.blocked_warning(item));
+                return Ok(());
+            }
+            self.set_status(index, status)?;
+            self.clamp_selection();
+        }
+        Ok(())
+    }
+
+    fn change_selected_priority(&mut self, change: fn(Priority) -> Priority) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let current = self.items[index].priority;
+            let priority = change(current);
+            if priority != current {
+                self.set_priority(index, priority)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn cycle_selected_recurrence(&mut self) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let recurrence = self.items[index].recurrence.next();
+            self.set_recurrence(index, recurrence)?;
+            self.status = Some(Status::Info(match recurrence {
+                Recurrence::None => "Task no longer repeats".to_string(),
+                _ => format!("Task repeats {}", recurrence.label()),
+            }));
+        }
+        Ok(())
+    }
+
+    fn cycle_selected_color(&mut self) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let color = self.items[index].color.next();
+            self.set_color(index, color)?;
+            self.status = Some(Status::Info(match color {
+                TaskColor::None => "Color cleared".to_string(),
+                _ => format!("Color set to {}", color.label()),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Pins the selected task above the rest of the list, or unpins it, keeping it selected
+    /// as it moves.
+    fn toggle_selected_pin(&mut self) -> Result<()> {
+        let Some((id, pinned)) = self.selected_item().map(|item| (item.id, !item.pinned)) else {
+            return Ok(());
+        };
+        db::set_pinned(&self.connection, id, pinned, Utc::now())?;
+        self.load_tasks()?;
+        self.select_id(id);
+        self.status = Some(Status::Info(
+            if pinned { "Pinned" } else { "Unpinned" }.to_string(),
+        ));
+        Ok(())
+    }
+
+    fn cycle_sort_mode(&mut self) -> Result<()> {
+        self.set_sort_mode(self.sort_mode.next())
+    }
+
+    fn set_sort_mode(&mut self, sort_mode: SortMode) -> Result<()> {
+        let selected_id = self.selected_item().map(|item| item.id);
+        self.sort_mode = sort_mode;
+        db::write_setting(&self.connection, SORT_SETTING, sort_mode.name())?;
+        self.load_tasks()?;
+        if let Some(id) = selected_id {
+            self.select_id(id);
+        }
+        Ok(())
+    }
+
+    /// Sets the sort and filters to the config's defaults, then to whichever of them were
+    /// changed and saved in the open list's database.
+    fn load_view_settings(&mut self) -> Result<()> {
+        self.sort_mode = self.default_sort;
+        self.hide_done = self.default_filter == Some(StartupFilter::HideDone);
+        self.priority_filter = self.default_filter.and_then(StartupFilter::priority);
+        self.tag_filter = None;
+
+        let read = |key| db::read_setting(&self.connection, key);
+        if let Some(sort_mode) = read(SORT_SETTING)?.and_then(|name| SortMode::from_name(&name)) {
+            self.sort_mode = sort_mode;
+        }
+        if let Some(hide_done) = read(HIDE_DONE_SETTING)? {
+            self.hide_done = hide_done == "true";
+        }
+        if let Some(priority) = read(PRIORITY_FILTER_SETTING)? {
+            self.priority_filter = priority.parse().ok().map(Priority::from_i64);
+        }
+        if let Some(tag) = read(TAG_FILTER_SETTING)? {
+            self.tag_filter = Some(tag).filter(|tag| !tag.is_empty());
+        }
+        Ok(())
+    }
+
+    fn cycle_theme(&mut self) -> Result<()> {
+        self.theme_name = self.theme_name.next();
+        self.apply_theme();
+        self.with_home_db(|connection| {
+            db::write_setting(connection, THEME_SETTING, self.theme_name.label())
+        })?;
+        self.status = Some(Status::Info(format!("Theme: {}", self.theme_name.label())));
+        Ok(())
+    }
+
+    fn apply_theme(&mut self) {
+        self.theme = match self.theme_name {
+            ThemeName::Dark => self.configured_theme,
+            ThemeName::Light => Theme::light(),
+            ThemeName::HighContrast => Theme::high_contrast(),
+        };
+    }
+
+    fn move_selected_down(&mut self) -> Result<()> {
+        if let Some(row) = self.state.selected()
+            && let Some(target_row) = self.sibling_row(row, true)
+        {
+            self.move_selected(row, target_row)?;
+        }
+        Ok(())
+    }
+
+    fn move_selected_up(&mut self) -> Result<()> {
+        if let Some(row) = self.state.selected()
+            && let Some(target_row) = self.sibling_row(row, false)
+        {
+            self.move_selected(row, target_row)?;
+        }
+        Ok(())
+    }
+
+    /// The nearest row after or before `row` on the same level, so a task swaps places
+    /// with the next task rather than its own subtasks, and a subtask stays under its parent.
+    fn sibling_row(&self, row: usize, forward: bool) -> Option<usize> {
+        let visible = self.visible_indices();
+        let subtasks = self.subtask_rows(&visible);
+        if row >= visible.len() {
+            return None;
+        }
+        let level = |row: usize| subtasks[row].then(|| self.items[visible[row]].parent_id);
+        let mut other = row;
+        loop {
+            other = if forward {
+                other + 1
+            } else {
+                other.checked_sub(1)?
+            };
+            if other >= visible.len() || subtasks[row] && !subtasks[other] {
+                return None;
+            }
+            if level(other) == level(row) {
+                return Some(other);
+            }
+        }
+    }
+
+    /// Moves the selected task to the top or bottom of the manual order, keeping it selected.
+    fn move_selected_to_end(&mut self, top: bool) -> Result<()> {
+        let Some(id) = self.selected_item().map(|item| item.id) else {
+            return Ok(());
+        };
+        if self.sort_mode != SortMode::Manual {
+            self.status = Some(Status::Error(
+                "Switch to Manual sort (s) to reorder tasks".to_string(),
+            ));
+            return Ok(());
+        }
+        with_transaction(&mut self.connection, |tx| {
+            if top {
+                db::move_to_top(tx, id)
+            } else {
+                db::move_to_bottom(tx, id)
+            }
+        })?;
+        self.load_tasks()?;
+        self.select_id(id);
+        Ok(())
+    }
+
+    fn move_selected(&mut self, row: usize, target_row: usize) -> Result<()> {
+        if self.sort_mode != SortMode::Manual {
+            self.status = Some(Status::Error(
+                "Switch to Manual sort (s) to reorder tasks".to_string(),
+            ));
+            return Ok(());
+        }
+        let visible = self.visible_indices();
+        let id = self.items[visible[row]].id;
+        self.swap_positions(visible[row], visible[target_row])?;
+        // Subtasks follow their parent, so the rows between may have moved too.
+        self.load_tasks()?;
+        self.select_id(id);
+        Ok(())
+    }
+
+    fn export(&mut self, export: fn(&[TodoItem], &Path) -> Result<PathBuf>) {
+        if self.refuse_in_demo() {
+            return;
+        }
+        let result = db::load_tasks(&self.connection, None, self.sort_mode)
+            .and_then(|items| Ok((items.len(), export(&items, &self.data_dir)?)));
+        self.status = Some(match result {
+            Ok((count, path)) => {
+                Status::Info(format!("Exported {} tasks to {}", count, path.display()))
+            }
+            Err(err) => Status::Error(format!("Export failed: {}", err)),
+        });
+    }
+
+    fn archive_selected(&mut self) -> Result<()> {
+        if let Some(index) = self.selected_index() {
+            let archived = !self.show_archived;
+            self.set_archived(index, archived)?;
+            self.clamp_selection();
+            self.status = Some(Status::Info(
+                if archived {
+                    "Task archived"
+                } else {
+                    "Task restored"
+                }
+                .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Shows only tasks of `priority`, or everything again when that filter is already on.
+    fn toggle_priority_filter(&mut self, priority: Priority) -> Result<()> {
+        let selected_id = self.selected_item().map(|item| item.id);
+        self.priority_filter = (self.priority_filter != Some(priority)).then_some(priority);
+        let saved = self
+            .priority_filter
+            .map(|priority| priority.as_i64().to_string());
+        db::write_setting(
+            &self.connection,
+            PRIORITY_FILTER_SETTING,
+            saved.as_deref().unwrap_or_default(),
+        )?;
+        self.sync_window();
+        if let Some(id) = selected_id {
+            self.select_id(id);
+        }
+        self.clamp_selection();
+        Ok(())
+    }
+
+    fn toggle_hide_done(&mut self) -> Result<()> {
+        let selected_id = self.selected_item().map(|item| item.id);
+        self.hide_done = !self.hide_done;
+        db::write_setting(
+            &self.connection,
+            HIDE_DONE_SETTING,
+            if self.hide_done { "true" } else { "false" },
+        )?;
+        self.sync_window();
+        if let Some(id) = selected_id {
+            self.select_id(id);
+        }
+        self.clamp_selection();
+        Ok(())
+    }
+
+    /// Switches to only the incomplete tasks that are due today or overdue, and back.
+    fn toggle_today_view(&mut self) {
+        match self.today_view.take() {
+            Some(previous) => {
+                self.sync_window();
+                self.reset_selection();
+                if let Some(id) = previous {
+                    self.select_id(id);
+                }
+            }
+            None => {
+                self.today_view = Some(self.selected_item().map(|item| item.id));
+                self.sync_window();
+                self.reset_selection();
+            }
+        }
+        self.clamp_selection();
+    }
+
+    fn toggle_archive_view(&mut self) -> Result<()> {
+        self.show_archived = !self.show_archived;
+        self.marked.clear();
+        self.load_tasks()?;
+        self.reset_selection();
+        Ok(())
+    }
+
+    fn import_json(&mut self, mode: ImportMode) -> Result<()> {
+        self.mode = Mode::Normal;
+        let tasks = match export::read_json(&self.data_dir) {
+            Ok(tasks) => tasks,
+            Err(err) => {
+                self.status = Some(Status::Error(format!("Import failed: {:#}", err)));
+                return Ok(());
+            }
+        };
+
+        let imported = self.import_tasks(&tasks, mode)?;
+        self.reset_selection();
+        self.status = Some(Status::Info(format!(
+            "Imported {} of {} tasks",
+            imported,
+            tasks.len()
+        )));
+        Ok(())
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(id) = self.selected_item().map(|item| item.id)
+            && !self.marked.remove(&id)
+        {
+            self.marked.insert(id);
+        }
+    }
+
+    fn toggle_marked(&mut self) -> Result<()> {
+        if self.marked.is_empty() {
+            return Ok(());
+        }
+        let mut marked = std::mem::take(&mut self.marked);
+        let before = marked.len();
+        marked.retain(|&id| {
+            !self
+                .items
+                .iter()
+                .any(|item| item.id == id && item.blocked && !item.is_done)
+        });
+        self.toggle_tasks(&marked)?;
+        self.clamp_selection();
+        self.status = Some(Status::Info(match before - marked.len() {
+            0 => format!("Toggled {} tasks", marked.len()),
+            blocked => format!("Toggled {} tasks, {} still blocked", marked.len(), blocked),
+        }));
+        self.mode = Mode::Normal;
+        Ok(())
+    }
+
+    fn delete_marked(&mut self) -> Result<()> {
+        let marked = std::mem::take(&mut self.marked);
+        self.delete_tasks(&marked)?;
+        self.clamp_selection();
+        self.status = Some(Status::Info(format!("Deleted {} tasks", marked.len())));
+        Ok(())
+    }
+
+    fn show_stats(&mut self) -> Result<()> {
+        self.tag_stats = db::tag_stats(&self.connection, self.show_archived)?;
+        let completed_at = db::completion_times(&self.connection)?;
+        let today = Local::now().date_naive();
+        self.streak = model::streak(&model::completion_dates(&completed_at, &Local), today);
+        self.completions_per_day =
+            model::completions_per_day(&completed_at, &Local, today, self.chart_days);
+        self.mode = Mode::Stats;
+        Ok(())
+    }
+
+    fn show_activity(&mut self) -> Result<()> {
+        self.activity = db::load_activity(&self.connection)?;
+        self.activity_state = ListState::default();
+        if !self.activity.is_empty() {
+            self.activity_state.select_first();
+        }
+        self.mode = Mode::Activity;
+        Ok(())
+    }
+
+    fn show_lists(&mut self) -> Result<()> {
+        let mut names = vec![db::DEFAULT_LIST.to_string()];
+        names.extend(self.with_home_db(db::list_names)?);
+        let active = self.list_name();
+        self.lists_state =
+            ListState::default().with_selected(names.iter().position(|name| name == active));
+        self.list_names = names;
+        self.mode = Mode::Lists;
+        Ok(())
+    }
+
+    /// The name of the list being shown.
+    fn list_name(&self) -> &str {
+        self.active_list.as_deref().unwrap_or(db::DEFAULT_LIST)
+    }
+
+    /// Runs `f` on the database the app was opened on, where the lists are named.
+    fn with_home_db<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        match (&self.active_list, &self.home_path) {
+            (Some(_), Some(home)) => f(&db::open(home)?),
+            _ => f(&self.connection),
+        }
+    }
+
+    /// Opens the list `name`, creating it if needed. Each list has its own database, so the
+    /// undo history, marks and filters of the old one are dropped, and the task last
+    /// selected in the new one is selected again.
+    fn switch_list(&mut self, name: &str) -> Result<()> {
+        self.mode = Mode::Normal;
+        let Some(home) = self.home_path.clone() else {
+            self.status = Some(Status::Error(
+                "Lists need a database file, this one is in memory".to_string(),
+            ));
+            return Ok(());
+        };
+        if name == self.list_name() {
+            return Ok(());
+        }
+        self.save_selection()?;
+
+        let list = (name != db::DEFAULT_LIST).then(|| name.to_string());
+        let path = match &list {
+            Some(name) => {
+                self.with_home_db(|connection| db::add_list(connection, name, Utc::now()))?;
+                db::list_path(&home, name)
+            }
+            None => home,
+        };
+        let connection = db::open(&path)?;
+        let wal = db::enable_wal(&connection);
+        db::init_schema(&connection)
+            .wrap_err_with(|| format!("failed to initialize {}", path.display()))?;
+        self.connection = connection;
+        self.active_list = list;
+
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.session_toggled.clear();
+        self.marked.clear();
+        self.search_buffer.clear();
+        self.window = None;
+        self.load_view_settings()?;
+        self.load_tasks()?;
+        self.reset_selection();
+        if let Some(id) = db::read_setting(&self.connection, SELECTED_SETTING)?
+            .and_then(|value| value.parse().ok())
+        {
+            self.select_id(id);
+        }
+        self.status = Some(match wal {
+            Ok(()) => Status::Info(format!("Opened list {}", name)),
+            Err(err) => Status::Error(format!("Opened list {}, WAL unavailable: {:#}", name, err)),
+        });
+        Ok(())
+    }
+
+    fn confirm_clear_completed(&mut self) {
+        if self.counts().done > 0 {
+            self.mode = Mode::ConfirmClearCompleted;
+        } else {
+            self.status = Some(Status::Error("No completed tasks to clear".to_string()));
+        }
+    }
+
+    fn clear_completed(&mut self) -> Result<()> {
+        let removed = self.delete_completed()?;
+        self.clamp_selection();
+        self.status = Some(Status::Info(format!("Cleared {} completed tasks", removed)));
+        Ok(())
+    }
+
+    fn confirm_delete_selected(&mut self, count: usize) {
+        if let Some(row) = self.state.selected()
+            && self.selected_item().is_some()
+        {
+            self.delete_count = count.min(self.visible_indices().len() - row);
+            self.mode = Mode::ConfirmDelete;
+        }
+    }
+
+    /// Deletes `delete_count` tasks starting at the selected one.
+    fn delete_selected(&mut self) -> Result<()> {
+        for _ in 0..std::mem::replace(&mut self.delete_count, 1) {
+            if let Some(index) = self.selected_index() {
+                self.delete_task(index)?;
+                self.clamp_selection();
+            }
+        }
+        Ok(())
+    }
+
+    fn clamp_selection(&mut self) {
+        let len = self.row_count();
+        if len == 0 {
+            self.state.select(None);
+        } else if self.state.selected().is_none_or(|row| row >= len) {
+            self.state.select(Some(len - 1));
+        }
+    }
+
+    fn reset_selection(&mut self) {
+        let has_rows = self.row_count() > 0;
+        self.state.select(has_rows.then_some(0));
+    }
+
+    /// Selectable rows: the visible tasks plus the collapsed completed row when it's shown.
+    fn row_count(&self) -> usize {
+        self.visible_indices().len() + usize::from(self.collapsed_count() > 0)
+    }
+
+    /// How many completed tasks the collapsed row stands for, counting only the ones the
+    /// filters and search would otherwise show.
+    fn collapsed_count(&self) -> usize {
+        if !self.completed_collapsed {
+            return 0;
+        }
+        let query = self.search_buffer.as_str().to_lowercase();
+        let today = Local::now().date_naive();
+        self.items
+            .iter()
+            .filter(|item| item.is_done && self.is_shown(item, today))
+            .filter(|item| search_task(item, &query).is_some())
+            .count()
+    }
+
+    /// Whether the collapsed completed row is selected.
+    fn summary_selected(&self) -> bool {
+        self.completed_collapsed
+            && self.state.selected() == Some(self.visible_indices().len())
+            && self.collapsed_count() > 0
+    }
+
+    /// Folds the completed tasks into one row, or unfolds them. Expanding from that row
+    /// selects the first of them.
+    fn toggle_completed_collapsed(&mut self) {
+        let selected_id = self.selected_item().map(|item| item.id);
+        let from_summary = self.summary_selected();
+        self.completed_collapsed = !self.completed_collapsed;
+        self.sync_window();
+        if from_summary {
+            let visible = self.visible_indices();
+            if let Some(row) = visible.iter().position(|&index| self.items[index].is_done) {
+                self.state.select(Some(row));
+            }
+        } else if let Some(id) = selected_id {
+            self.select_id(id);
+        }
+        self.clamp_selection();
+    }
+
+    /// Whether `item` passes the tag, done, priority and today filters.
+    fn is_shown(&self, item: &TodoItem, today: NaiveDate) -> bool {
+        self.tag_filter
+            .as_ref()
+            .is_none_or(|tag| item.tags.contains(tag))
+            && !(self.hide_done && item.is_done)
+            && self
+                .priority_filter
+                .is_none_or(|priority| item.priority == priority)
+            && (self.today_view.is_none()
+                || !item.is_done && item.due_date.is_some_and(|due| due <= today))
+    }
+
+    /// Indices into `items` of the tasks currently shown, in display order. While searching,
+    /// description matches come first, best fuzzy match first, then tag and notes matches.
+    fn visible_indices(&self) -> Vec<usize> {
+        let query = self.search_buffer.as_str().to_lowercase();
+        let today = Local::now().date_naive();
+        let mut scored: Vec<(usize, (SearchField, i64))> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| self.is_shown(item, today))
+            .filter(|(_, item)| !(self.completed_collapsed && item.is_done))
+            .filter_map(|(index, item)| search_task(item, &query).map(|found| (index, found)))
+            .collect();
+        if self.today_view.is_some() {
+            scored.sort_by_key(|&(index, _)| {
+                let item = &self.items[index];
+                (
+                    item.due_date,
+                    item.due_time,
+                    Reverse(item.priority.as_i64()),
+                )
+            });
+        }
+        if !query.is_empty() {
+            scored.sort_by_key(|&(_, (field, score))| (field, Reverse(score)));
+        }
+        scored.into_iter().map(|(index, _)| index).collect()
+    }
+
+    /// The visible tasks with a heading before each category, or just the tasks when none
+    /// of them has a category, search results are ranked or the today view is open.
+    fn list_rows(&self) -> Vec<ListRow> {
+        let visible = self.visible_indices();
+        let grouped = self.search_buffer.is_empty()
+            && self.today_view.is_none()
+            && visible
+                .iter()
+                .any(|&index| self.items[index].category.is_some());
+        let subtasks = self.subtask_rows(&visible);
+        let mut rows = Vec::new();
+        let mut current = None;
+        for (row, &index) in visible.iter().enumerate() {
+            // Subtasks stay under their parent's heading whatever their own project is, and
+            // pinned tasks sit above every heading.
+            if !subtasks[row] && self.items[index].pinned {
+                current = None;
+            } else if !subtasks[row] {
+                let category = &self.items[index].category;
+                if grouped && (row == 0 || current != Some(category)) {
+                    rows.push(ListRow::Header(category.clone()));
+                }
+                current = Some(category);
+            }
+            rows.push(ListRow::Task(row));
+        }
+        match self.collapsed_count() {
+            0 => {}
+            count => rows.push(ListRow::Completed(count)),
+        }
+        rows
+    }
+
+    /// Columns taken by the line number column and the space after it, or zero when the
+    /// numbers are hidden.
+    fn line_number_width(&self) -> usize {
+        if self.line_numbers {
+            let count = match self.window {
+                Some(window) => window.counts.total,
+                None => self.visible_indices().len(),
+            };
+            count.max(1).to_string().len() + 1
+        } else {
+            0
+        }
+    }
+
+    /// Opens the add prompt for a step under the selected task, or under its parent when a
+    /// subtask is selected, so nesting stays one level deep.
+    fn start_subtask(&mut self) {
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let parent_id = item
+            .parent_id
+            .filter(|&parent_id| self.items.iter().any(|item| item.id == parent_id))
+            .unwrap_or(item.id);
+        self.subtask_parent = Some(parent_id);
+        self.input_buffer.clear();
+        self.mode = Mode::Input;
+    }
+
+    /// For each visible row, whether it is a subtask shown under its parent. Ranked search
+    /// results and the today view are flat.
+    fn subtask_rows(&self, visible: &[usize]) -> Vec<bool> {
+        if !self.search_buffer.is_empty() || self.today_view.is_some() {
+            return vec![false; visible.len()];
+        }
+        let ids: HashSet<i64> = visible.iter().map(|&index| self.items[index].id).collect();
+        visible
+            .iter()
+            .map(|&index| {
+                self.items[index]
+                    .parent_id
+                    .is_some_and(|parent_id| ids.contains(&parent_id))
+            })
+            .collect()
+    }
+
+    /// Done and total subtask counts for each task that has subtasks.
+    fn subtask_progress(&self) -> HashMap<i64, (usize, usize)> {
+        let mut progress: HashMap<i64, (usize, usize)> = HashMap::new();
+        for item in &self.items {
+            if let Some(parent_id) = item.parent_id {
+                let (done, total) = progress.entry(parent_id).or_default();
+                *done += item.is_done as usize;
+                *total += 1;
+            }
+        }
+        progress
+    }
+
+    fn enter_focus(&mut self) {
+        if self.selected_item().is_some() {
+            self.mode = Mode::Focus;
+        }
+    }
+
+    fn toggle_line_numbers(&mut self) {
+        self.line_numbers = !self.line_numbers;
+    }
+
+    /// How many unfinished tasks were added more than `stale_after_days` ago.
+    fn stale_count(&self, now: DateTime<Utc>) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.is_stale(now, self.stale_after_days))
+            .count()
+    }
+
+    /// Counts over the whole list, even when only a window of it is loaded.
+    fn counts(&self) -> TaskCounts {
+        match self.window {
+            Some(window) => window.counts,
+            None => TaskCounts {
+                total: self.items.len(),
+                done: self.items.iter().filter(|item| item.is_done).count(),
+                stale: self.stale_count(Utc::now()),
+            },
+        }
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let row = self.state.selected()?;
+        self.visible_indices().get(row).copied()
+    }
+
+    fn selected_item(&self) -> Option<&TodoItem> {
+        self.selected_index().map(|index| &self.items[index])
+    }
+
+    /// Selects the task `id` if it is shown, moving the window to it when it isn't loaded.
+    fn select_id(&mut self, id: i64) {
+        let row_of = |app: &Self| {
+            app.visible_indices()
+                .iter()
+                .position(|&index| app.items[index].id == id)
+        };
+        let mut row = row_of(self);
+        if row.is_none() && self.window.is_some() {
+            match db::task_position(&self.connection, self.show_archived, self.sort_mode, id) {
+                Ok(Some(position)) => {
+                    self.load_position(position);
+                    row = row_of(self);
+                }
+                Ok(None) => {}
+                Err(err) => self.report_error(err),
+            }
+        }
+        if let Some(row) = row {
+            self.state.select(Some(row));
+        }
+    }
+
+    fn select_next_by(&mut self, count: usize) {
+        let position = self
+            .selected_position()
+            .map_or(0, |position| position.saturating_add(count));
+        self.select_position(position);
+    }
+
+    /// Selects the given visible row, clamped to the last one.
+    fn select_row(&mut self, row: usize) {
+        if let Some(last) = self.row_count().checked_sub(1) {
+            self.state.select(Some(row.min(last)));
+        }
+    }
+
+    fn select_previous_by(&mut self, count: usize) {
+        if self.row_count() > 0 {
+            let position = self
+                .selected_position()
+                .map_or(0, |position| position.saturating_sub(count));
+            self.select_position(position);
+        }
+    }
+
+    fn select_next(&mut self) {
+        self.select_next_by(1);
+    }
+
+    fn select_previous(&mut self) {
+        self.select_previous_by(1);
+    }
+
+    fn select_next_incomplete(&mut self) {
+        self.select_incomplete(true);
+    }
+
+    fn select_prev_incomplete(&mut self) {
+        self.select_incomplete(false);
+    }
+
+    /// Moves to the nearest unfinished task after or before the selection, wrapping around
+    /// the end of the list.
+    fn select_incomplete(&mut self, forward: bool) {
+        let visible = self.visible_indices();
+        let len = visible.len();
+        let start =
+            self.state
+                .selected()
+                .unwrap_or(if forward { len.saturating_sub(1) } else { 0 });
+        let found = (1..=len)
+            .map(|step| {
+                if forward {
+                    (start + step) % len
+                } else {
+                    (start + len - step % len) % len
+                }
+            })
+            .find(|&row| !self.items[visible[row]].is_done);
+        match found {
+            Some(row) => self.state.select(Some(row)),
+            None => {
+                self.status = Some(Status::Info("No incomplete tasks".to_string()));
+            }
+        }
+    }
+
+    /// The line of the selected task among `rows`. The collapsed completed row comes after
+    /// every task, so it's the line when no task matched.
+    fn selected_line(&self, rows: &[ListRow]) -> Option<usize> {
+        let selected = self.state.selected()?;
+        rows.iter().position(|row| match row {
+            ListRow::Task(row) => *row == selected,
+            ListRow::Completed(_) => true,
+            ListRow::Header(_) => false,
+        })
+    }
+
+    /// Lines per page in the paged view: as many as fit in the list.
+    fn page_size(&self) -> usize {
+        (self.list_area.height as usize).max(1)
+    }
+
+    /// The page holding the selection, counted from zero.
+    fn current_page(&self, rows: &[ListRow]) -> usize {
+        self.selected_line(rows).unwrap_or(0) / self.page_size()
+    }
+
+    fn page_count(&self, rows: &[ListRow]) -> usize {
+        rows.len().div_ceil(self.page_size()).max(1)
+    }
+
+    fn toggle_paged(&mut self) {
+        self.paged = !self.paged;
+        self.status = Some(Status::Info(
+            if self.paged {
+                "Paged view, Ctrl+f / Ctrl+b change pages"
+            } else {
+                "Scrolling view"
+            }
+            .to_string(),
+        ));
+    }
+
+    /// Selects the first task on the next or previous page. Outside the paged view this
+    /// pages down or up like PgDn and PgUp.
+    fn turn_page(&mut self, forward: bool) {
+        if !self.paged {
+            if forward {
+                self.select_page_down();
+            } else {
+                self.select_page_up();
+            }
+            return;
+        }
+
+        let rows = self.list_rows();
+        let page = self.current_page(&rows);
+        let target = if forward {
+            page + 1
+        } else {
+            page.saturating_sub(1)
+        };
+        if target >= self.page_count(&rows) {
+            return;
+        }
+        let first_task = rows
+            .iter()
+            .skip(target * self.page_size())
+            .find_map(|row| match row {
+                ListRow::Task(row) => Some(*row),
+                ListRow::Header(_) | ListRow::Completed(_) => None,
+            });
+        if let Some(row) = first_task {
+            self.state.select(Some(row));
+        }
+    }
+
+    fn select_page_down(&mut self) {
+        if self.row_count() > 0 {
+            self.select_next_by(self.page_size());
+        }
+    }
+
+    fn select_page_up(&mut self) {
+        self.select_previous_by(self.page_size());
+    }
+
+    fn select_first(&mut self) {
+        self.select_position(0);
+    }
+
+    fn select_last(&mut self) {
+        self.select_position(usize::MAX);
+    }
+}
+
+/// Matches a lowercased `query` against `item` and returns where it matched along with the
+/// score. The description and tags are fuzzy matched; notes are too long for a subsequence to
+/// mean much, so they have to contain the query.
+fn search_task(item: &TodoItem, query: &str) -> Option<(SearchField, i64)> {
+    if let Some(found) = fuzzy_match(&item.description, query) {
+        return Some((SearchField::Description, found.score));
+    }
+    if let Some(score) = item
+        .tags
+        .iter()
+        .filter_map(|tag| fuzzy_match(tag, query))
+        .map(|found| found.score)
+        .max()
+    {
+        return Some((SearchField::Tag, score));
+    }
+    item.notes
+        .to_lowercase()
+        .contains(query)
+        .then_some((SearchField::Notes, 0))
+}
+
+/// The editor from `$EDITOR`, if it is set to something other than whitespace.
+fn editor_command() -> Option<String> {
+    env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+}
+
+/// Runs `editor` on `path`. Extra words in the variable, as in `code --wait`, are passed
+/// as arguments before the path.
+fn run_editor(editor: &str, path: &Path) -> Result<()> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let status = process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .wrap_err_with(|| format!("failed to launch {}", editor))?;
+    if !status.success() {
+        bail!("{} exited with {}, notes left unchanged", editor, status);
+    }
+    Ok(())
+}
+
+/// Hands the terminal back to the shell so an external program can use it.
+fn suspend_terminal() -> Result<()> {
+    execute!(
+        io::stdout(),
+        DisableBracketedPaste,
+        DisableMouseCapture,
+        LeaveAlternateScreen
+    )?;
+    disable_raw_mode()?;
+    Ok(())
+}
+
+fn resume_terminal(terminal: &mut DefaultTerminal) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    terminal.clear()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{buffer::Buffer, widgets::Widget};
+
+    use super::*;
+    use crate::config::CONFIG_FILE;
+
+    /// An app on an in-memory database. Its data directory is named after the running test,
+    /// like [`test_dir`], but only created if the test writes there.
+    fn app() -> App {
+        let name = thread::current().name().unwrap_or("app").replace("::", "-");
+        let data_dir = env::temp_dir().join(format!("task-manager-tui-{}-{}", name, process::id()));
+        App::new(Connection::open_in_memory().unwrap(), data_dir).unwrap()
+    }
+
+    /// An empty directory of files for the test `name`, so tests running in parallel never
+    /// share one. The test removes it after its assertions.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("task-manager-tui-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn stored_done(app: &App, id: i64) -> bool {
+        app.connection
+            .query_row("SELECT is_done FROM tasks WHERE id = ?1", [id], |row| {
+                row.get::<_, i32>(0)
+            })
+            .unwrap()
+            != 0
+    }
+
+    fn task_count(app: &App) -> i64 {
+        app.connection
+            .query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn add_task_returns_the_inserted_row_id() {
+        let mut app = app();
+
+        let first = app.add_task("first").unwrap();
+        let second = app.add_task("second #work").unwrap();
+
+        let stored: i64 = app
+            .connection
+            .query_row(
+                "SELECT id FROM tasks WHERE description = 'second'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(second, stored);
+        assert_ne!(first, second);
+        let ids: Vec<i64> = app.items.iter().map(|item| item.id).collect();
+        assert_eq!(ids, [first, second]);
+        assert_eq!(app.items[1].description, "second");
+        assert_eq!(app.items[1].tags, ["#work"]);
+    }
+
+    #[test]
+    fn typing_a_tag_offers_existing_ones_to_complete_it() {
+        let mut app = app();
+        app.add_task("tidy desk #home").unwrap();
+        app.add_task("maths #homework").unwrap();
+        app.add_task("report #work").unwrap();
+        app.mode = Mode::Input;
+        let press = |app: &mut App, code| {
+            app.handle_event(KeyEvent::new(code, KeyModifiers::NONE))
+                .unwrap()
+        };
+        for c in "buy paint #HO".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert_eq!(app.tag_suggestions, ["#home", "#homework"]);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let tag_column = |y| (0..80).find(|&x| buf[(x, y)].symbol() == "#");
+        assert_eq!(tag_column(18), tag_column(22));
+        assert_eq!(buf[(tag_column(19).unwrap() + 5, 19)].symbol(), "w");
+
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.input_buffer.as_str(), "buy paint #homework ");
+        assert!(app.tag_suggestions.is_empty());
+
+        for c in "#zz".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert!(app.tag_suggestions.is_empty());
+        press(&mut app, KeyCode::Enter);
+        assert!(app.mode == Mode::Normal);
+        assert_eq!(app.items[3].tags, ["#homework", "#zz"]);
+    }
+
+    #[test]
+    fn leaving_a_prompt_with_typed_text_asks_first() {
+        let mut app = app();
+        app.add_task("water plants").unwrap();
+        app.state.select(Some(0));
+        let press = |app: &mut App, code, modifiers| {
+            app.handle_event(KeyEvent::new(code, modifiers)).unwrap()
+        };
+
+        app.mode = Mode::Input;
+        app.input_buffer.set("a long description".to_string());
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(
+            app.mode
+                == Mode::ConfirmDiscard {
+                    quit: false,
+                    editing: false
+                }
+        );
+        press(&mut app, KeyCode::Char('n'), KeyModifiers::NONE);
+        assert!(app.mode == Mode::Input);
+        assert_eq!(app.input_buffer.as_str(), "a long description");
+
+        press(&mut app, KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(
+            app.mode
+                == Mode::ConfirmDiscard {
+                    quit: true,
+                    editing: false
+                }
+        );
+        press(&mut app, KeyCode::Char('y'), KeyModifiers::NONE);
+        assert!(app.should_exit && app.mode == Mode::Normal);
+        assert!(app.input_buffer.is_empty());
+
+        app.should_exit = false;
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::NONE);
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(app.mode == Mode::Normal);
+    }
+
+    #[test]
+    fn failed_add_keeps_the_typed_text_for_a_retry() {
+        let mut app = app();
+        app.connection
+            .execute(
+                "CREATE TRIGGER reject_adds BEFORE INSERT ON tasks
+                 BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+                [],
+            )
+            .unwrap();
+        app.mode = Mode::Input;
+        app.input_buffer.set("water plants".to_string());
+
+        app.submit_input().unwrap();
+        assert!(app.mode == Mode::Input);
+        assert_eq!(app.input_buffer.as_str(), "water plants");
+        assert!(
+            matches!(&app.status, Some(Status::Error(message)) if message.contains("disk full"))
+        );
+
+        app.connection
+            .execute("DROP TRIGGER reject_adds", [])
+            .unwrap();
+        app.submit_input().unwrap();
+        assert!(app.mode == Mode::Normal);
+        assert!(app.input_buffer.is_empty());
+        assert_eq!(app.items[0].description, "water plants");
+    }
+
+    #[test]
+    fn pasted_newlines_only_survive_in_notes() {
+        let mut app = app();
+        app.mode = Mode::Input;
+        app.input_buffer.set("buy ".to_string());
+        app.handle_paste("milk\r\nand eggs").unwrap();
+        assert!(app.mode == Mode::Input);
+        assert_eq!(app.input_buffer.as_str(), "buy milk and eggs");
+        app.submit_input().unwrap();
+        assert_eq!(app.items[0].description, "buy milk and eggs");
+
+        app.edit_selected_notes();
+        app.handle_paste("first\nsecond").unwrap();
+        app.submit_notes().unwrap();
+        assert_eq!(app.items[0].notes, "first\nsecond");
+    }
+
+    #[test]
+    fn incomplete_jumps_skip_done_tasks_and_wrap() {
+        let mut app = app();
+        for description in ["one", "two", "three", "four"] {
+            app.add_task(description).unwrap();
+        }
+        app.toggle_task(1).unwrap();
+        app.toggle_task(2).unwrap();
+        app.state.select(Some(0));
+
+        app.select_next_incomplete();
+        assert_eq!(app.selected_item().unwrap().description, "four");
+        app.select_next_incomplete();
+        assert_eq!(app.selected_item().unwrap().description, "one");
+        app.select_prev_incomplete();
+        assert_eq!(app.selected_item().unwrap().description, "four");
+
+        app.toggle_task(0).unwrap();
+        app.toggle_task(3).unwrap();
+        app.state.select(Some(1));
+        app.select_next_incomplete();
+        assert_eq!(app.state.selected(), Some(1));
+        assert!(matches!(app.status, Some(Status::Info(_))));
+    }
+
+    #[test]
+    fn toggle_task_flips_the_item_and_the_database() {
+        let mut app = app();
+        let id = app.add_task("task").unwrap();
+
+        app.toggle_task(0).unwrap();
+        assert!(app.items[0].is_done);
+        assert!(app.items[0].completed_at.is_some());
+        assert!(stored_done(&app, id));
+
+        app.toggle_task(0).unwrap();
+        assert!(!app.items[0].is_done);
+        assert!(app.items[0].completed_at.is_none());
+        assert!(!stored_done(&app, id));
+    }
+
+    #[test]
+    fn delete_task_removes_the_item_and_the_row() {
+        let mut app = app();
+        let first = app.add_task("first").unwrap();
+        app.add_task("second").unwrap();
+        let third = app.add_task("third").unwrap();
+
+        app.delete_task(1).unwrap();
+
+        let ids: Vec<i64> = app.items.iter().map(|item| item.id).collect();
+        assert_eq!(ids, [first, third]);
+        assert_eq!(task_count(&app), 2);
+    }
+
+    #[test]
+    fn activity_log_keeps_deleted_descriptions_newest_first() {
+        let mut app = app();
+        app.add_task("water plants").unwrap();
+        app.add_task("file taxes").unwrap();
+        app.toggle_task(0).unwrap();
+        app.toggle_task(0).unwrap();
+        app.delete_task(1).unwrap();
+
+        app.show_activity().unwrap();
+        let log: Vec<(Activity, &str)> = app
+            .activity
+            .iter()
+            .map(|entry| (entry.activity, entry.description.as_str()))
+            .collect();
+        assert_eq!(
+            log,
+            [
+                (Activity::Deleted, "file taxes"),
+                (Activity::Reopened, "water plants"),
+                (Activity::Completed, "water plants"),
+                (Activity::Added, "file taxes"),
+                (Activity::Added, "water plants"),
+            ]
+        );
+        assert!(app.mode == Mode::Activity);
+        assert_eq!(app.activity_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn delete_task_out_of_range_changes_nothing() {
+        let mut app = app();
+        app.add_task("only").unwrap();
+
+        app.delete_task(5).unwrap();
+
+        assert_eq!(app.items.len(), 1);
+        assert_eq!(task_count(&app), 1);
+    }
+
+    #[test]
+    fn load_tasks_round_trips_in_memory_changes() {
+        let mut app = app();
+        app.add_task("water plants #home").unwrap();
+        app.add_task("file taxes").unwrap();
+        app.toggle_task(1).unwrap();
+        app.set_priority(0, Priority::High).unwrap();
+        let before: Vec<(i64, String, bool, i64, Vec<String>)> = app
+            .items
+            .iter()
+            .map(|item| {
+                (
+                    item.id,
+                    item.description.clone(),
+                    item.is_done,
+                    item.priority.as_i64(),
+                    item.tags.clone(),
+                )
+            })
+            .collect();
+
+        app.load_tasks().unwrap();
+
+        let after: Vec<(i64, String, bool, i64, Vec<String>)> = app
+            .items
+            .iter()
+            .map(|item| {
+                (
+                    item.id,
+                    item.description.clone(),
+                    item.is_done,
+                    item.priority.as_i64(),
+                    item.tags.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn move_to_end_keeps_the_moved_task_selected() {
+        let mut app = app();
+        let first = app.add_task("first").unwrap();
+        app.add_task("second").unwrap();
+        let third = app.add_task("third").unwrap();
+        app.select_id(third);
+
+        app.move_selected_to_end(true).unwrap();
+        assert_eq!(app.items[0].id, third);
+        assert_eq!(app.selected_item().map(|item| item.id), Some(third));
+
+        app.select_id(first);
+        app.move_selected_to_end(false).unwrap();
+        assert_eq!(app.items[2].id, first);
+        assert_eq!(app.selected_item().map(|item| item.id), Some(first));
+    }
+
+    #[test]
+    fn toggle_all_completes_every_visible_task_then_reopens_them() {
+        let mut app = app();
+        app.add_task("first #work").unwrap();
+        app.add_task("second #work").unwrap();
+        app.add_task("third #home").unwrap();
+        app.toggle_task(0).unwrap();
+        app.tag_filter = Some("#work".to_string());
+
+        app.toggle_all().unwrap();
+        let done: Vec<bool> = app.items.iter().map(|item| item.is_done).collect();
+        assert_eq!(done, [true, true, false]);
+
+        app.toggle_all().unwrap();
+        let done: Vec<bool> = app.items.iter().map(|item| item.is_done).collect();
+        assert_eq!(done, [false, false, false]);
+    }
+
+    #[test]
+    fn list_rows_head_each_category_once() {
+        let mut app = app();
+        app.add_task("loose").unwrap();
+        app.add_task("project: work report").unwrap();
+        app.add_task("project: work review").unwrap();
+
+        assert_eq!(
+            app.list_rows(),
+            [
+                ListRow::Header(Some("work".to_string())),
+                ListRow::Task(0),
+                ListRow::Task(1),
+                ListRow::Header(None),
+                ListRow::Task(2),
+            ]
+        );
+        assert_eq!(app.items[0].editable_text(), "project: work report");
+    }
+
+    #[test]
+    fn subtasks_nest_under_their_parent_and_move_with_it() {
+        let mut app = app();
+        app.add_task("project: home move house").unwrap();
+        app.add_task("project: home paint").unwrap();
+        app.state.select(Some(0));
+        app.start_subtask();
+        app.input_buffer.set("pack boxes".to_string());
+        app.submit_input().unwrap();
+        app.start_subtask();
+        app.input_buffer.set("book van".to_string());
+        app.submit_input().unwrap();
+
+        let shown = |app: &App| -> Vec<String> {
+            app.visible_indices()
+                .into_iter()
+                .map(|index| app.items[index].description.clone())
+                .collect()
+        };
+        assert_eq!(
+            shown(&app),
+            ["move house", "pack boxes", "book van", "paint"]
+        );
+        assert_eq!(app.items[1].category.as_deref(), Some("home"));
+        assert_eq!(app.list_rows().len(), 5);
+        assert_eq!(app.subtask_progress()[&app.items[0].id], (0, 2));
+
+        app.state.select(Some(0));
+        app.move_selected_down().unwrap();
+        assert_eq!(
+            shown(&app),
+            ["paint", "move house", "pack boxes", "book van"]
+        );
+        assert_eq!(app.state.selected(), Some(1));
+
+        app.state.select(Some(3));
+        app.move_selected_down().unwrap();
+        assert_eq!(app.state.selected(), Some(3));
+        app.move_selected_up().unwrap();
+        assert_eq!(
+            shown(&app),
+            ["paint", "move house", "book van", "pack boxes"]
+        );
+    }
+
+    #[test]
+    fn list_rows_skip_headings_without_categories() {
+        let mut app = app();
+        app.add_task("first").unwrap();
+        app.add_task("second").unwrap();
+
+        assert_eq!(app.list_rows(), [ListRow::Task(0), ListRow::Task(1)]);
+    }
+
+    #[test]
+    fn today_view_shows_due_tasks_by_date_then_priority() {
+        let mut app = app();
+        let today = Local::now().date_naive();
+        let due = [
+            ("later", today.succ_opt(), Priority::High),
+            ("due today", Some(today), Priority::Normal),
+            ("urgent today", Some(today), Priority::High),
+            ("overdue", today.pred_opt(), Priority::Low),
+            ("no date", None, Priority::High),
+        ];
+        for (description, due_date, priority) in due {
+            app.add_task(description).unwrap();
+            let id = app.items.last().unwrap().id;
+            db::set_due_date(&app.connection, id, due_date, None, Utc::now()).unwrap();
+            db::set_priority(&app.connection, id, priority, Utc::now()).unwrap();
+        }
+        app.load_tasks().unwrap();
+        app.select_row(4);
+
+        app.toggle_today_view();
+        let shown: Vec<&str> = app
+            .visible_indices()
+            .into_iter()
+            .map(|index| app.items[index].description.as_str())
+            .collect();
+        assert_eq!(shown, ["overdue", "urgent today", "due today"]);
+        assert_eq!(app.state.selected(), Some(0));
+
+        app.toggle_today_view();
+        assert_eq!(app.selected_item().unwrap().description, "no date");
+    }
+
+    #[test]
+    fn due_dates_accept_a_time_and_reject_malformed_input() {
+        let mut app = app();
+        app.add_task("dentist").unwrap();
+        app.state.select(Some(0));
+        app.mode = Mode::DueDate;
+
+        app.input_buffer.set("2024-03-01 9:75".to_string());
+        app.submit_due_date().unwrap();
+        assert!(app.mode == Mode::DueDate);
+        assert!(matches!(app.status, Some(Status::Error(_))));
+
+        app.input_buffer.set("2024-03-01 09:15".to_string());
+        app.submit_due_date().unwrap();
+        assert!(app.mode == Mode::Normal);
+        app.load_tasks().unwrap();
+        assert_eq!(
+            app.items[0].due_label().as_deref(),
+            Some("2024-03-01 09:15")
+        );
+
+        let now = app.items[0]
+            .due_date
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+        assert!(app.items[0].is_due_within(now, REMINDER_WINDOW));
+        assert!(!app.items[0].is_due_within(now - TimeDelta::hours(1), REMINDER_WINDOW));
+    }
+
+    #[test]
+    fn counts_unfinished_tasks_older_than_the_threshold_as_stale() {
+        let mut app = app();
+        app.add_task("old").unwrap();
+        app.add_task("old but done").unwrap();
+        app.add_task("new").unwrap();
+        app.connection
+            .execute(
+                "UPDATE tasks SET created_at = '2024-03-01T09:00:00Z' WHERE description LIKE 'old%'",
+                [],
+            )
+            .unwrap();
+        app.load_tasks().unwrap();
+        let done = app.items[1].id;
+        db::set_done(&app.connection, done, true, Utc::now()).unwrap();
+        app.load_tasks().unwrap();
+
+        assert_eq!(app.stale_count(Utc::now()), 1);
+        app.stale_after_days = 0;
+        assert_eq!(app.stale_count(Utc::now()), 0);
+    }
+
+    #[test]
+    fn tiny_terminals_render_a_message_instead_of_the_layout() {
+        let mut app = app();
+        app.add_task("first").unwrap();
+
+        for (width, height) in [(0, 0), (1, 1), (80, 3), (10, 24)] {
+            let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+            (&mut app).render(buf.area, &mut buf);
+        }
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 3));
+        (&mut app).render(buf.area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("Terminal too small"));
+        assert_eq!(app.list_area, Rect::default());
+    }
+
+    #[test]
+    fn long_lists_render_only_the_window_and_suggest_archiving() {
+        let mut app = app();
+        app.large_list_warning = 20;
+        for n in 1..=30 {
+            app.add_task(&format!("task {:02}", n)).unwrap();
+        }
+        app.state.select(Some(24));
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("task 25") && !text.contains("task 01"));
+        assert!(text.contains("30 tasks loaded"));
+
+        app.large_list_warning = 0;
+        (&mut app).render(buf.area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(!text.contains("tasks loaded"));
+    }
+
+    #[test]
+    fn windowed_loading_follows_the_selection_through_the_list() {
+        let mut app = app();
+        for n in 1..=30 {
+            app.add_task(&format!("task {:02}", n)).unwrap();
+        }
+        app.load_window = 10;
+        app.load_tasks().unwrap();
+        app.reset_selection();
+        app.toggle_task(0).unwrap();
+        app.refresh_counts().unwrap();
+        assert_eq!(app.items.len(), 10);
+        let counts = app.counts();
+        assert_eq!((counts.total, counts.done), (30, 1));
+
+        app.select_last();
+        assert_eq!(app.window.unwrap().start, 20);
+        assert_eq!(app.selected_item().unwrap().description, "task 30");
+        app.select_previous_by(15);
+        assert_eq!(app.selected_item().unwrap().description, "task 15");
+        app.select_first();
+        assert_eq!(app.window.unwrap().start, 0);
+        for _ in 0..12 {
+            app.select_next();
+        }
+        assert_eq!(app.selected_item().unwrap().description, "task 13");
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("task 13") && text.contains("1/30 done"));
+
+        // A search needs every task loaded, and clearing it goes back to a window.
+        app.search_buffer.set("task 2".to_string());
+        app.sync_window();
+        assert!(app.window.is_none() && app.items.len() == 30);
+        app.select_id(app.items[25].id);
+        app.clear_search();
+        assert_eq!(app.items.len(), 10);
+        assert_eq!(app.selected_item().unwrap().description, "task 26");
+
+        app.execute_command(Command::Delete(2)).unwrap();
+        app.refresh_counts().unwrap();
+        assert_eq!(app.counts().total, 29);
+        assert!(!app.items.iter().any(|item| item.description == "task 02"));
+    }
+
+    #[test]
+    fn clearing_completed_records_tasks_outside_the_window() {
+        let mut app = app();
+        for n in 1..=30 {
+            app.add_task(&format!("task {:02}", n)).unwrap();
+        }
+        for done in [1, 24] {
+            db::set_done(&app.connection, app.items[done].id, true, Utc::now()).unwrap();
+        }
+        app.load_window = 10;
+        app.load_tasks().unwrap();
+        assert!(!app.items.iter().any(|item| item.description == "task 25"));
+
+        app.execute_command(Command::ClearCompleted).unwrap();
+        let deleted = db::load_activity(&app.connection)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.activity == Activity::Deleted)
+            .count();
+        assert_eq!(deleted, 2);
+
+        app.undo().unwrap();
+        app.undo().unwrap();
+        let stored = db::load_tasks(&app.connection, None, SortMode::Id).unwrap();
+        assert_eq!(stored.len(), 30);
+        assert!(stored[24].is_done);
+    }
+
+    #[test]
+    fn jumps_and_pages_reach_past_the_loaded_window() {
+        let mut app = app();
+        for n in 1..=30 {
+            app.add_task(&format!("task {:02}", n)).unwrap();
+        }
+        app.load_window = 10;
+        app.load_tasks().unwrap();
+        app.list_area = Rect::new(0, 0, 80, 12);
+        let press = |app: &mut App, keys: &str| {
+            for c in keys.chars() {
+                app.handle_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                    .unwrap();
+            }
+        };
+        let selected = |app: &App| app.selected_item().unwrap().description.clone();
+
+        app.select_last();
+        press(&mut app, "gg");
+        assert_eq!(selected(&app), "task 01");
+        press(&mut app, "25G");
+        assert_eq!(selected(&app), "task 25");
+        press(&mut app, "3gg");
+        assert_eq!(selected(&app), "task 03");
+
+        app.handle_event(KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(selected(&app), "task 15");
+        app.handle_event(KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE))
+            .unwrap();
+        app.handle_event(KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(selected(&app), "task 30");
+        app.handle_event(KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(selected(&app), "task 18");
+    }
+
+    #[test]
+    fn scrollbar_only_shows_when_the_list_overflows() {
+        let mut app = app();
+        let render = |app: &mut App| {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+            app.render(buf.area, &mut buf);
+            buf.content
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+        };
+        for n in 1..=5 {
+            app.add_task(&format!("task {}", n)).unwrap();
+        }
+        assert!(!render(&mut app).contains('║'));
+
+        for n in 6..=40 {
+            app.add_task(&format!("task {}", n)).unwrap();
+        }
+        assert!(render(&mut app).contains('║'));
+
+        app.search_buffer.set("task 3".to_string());
+        app.reset_selection();
+        assert!(!render(&mut app).contains('║'));
+    }
+
+    #[test]
+    fn demo_mode_is_marked_and_never_checkpoints() {
+        let mut app = app().demo();
+        assert!(app.status.is_none());
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("[DEMO]"));
+
+        app.execute_command(Command::Write).unwrap();
+        assert!(matches!(&app.status, Some(Status::Info(message)) if message.contains("Demo")));
+    }
+
+    #[test]
+    fn demo_mode_never_exports_or_yanks_into_the_data_dir() {
+        let mut app = app().demo();
+        app.add_task("water plants").unwrap();
+
+        app.export(export::export_json);
+        app.yank_selected();
+
+        assert!(!export::export_path(&app.data_dir, export::JSON_FILE).exists());
+        assert!(!app.data_dir.join("yank.txt").exists());
+        assert!(matches!(&app.status, Some(Status::Info(message)) if message.contains("Demo")));
+    }
+
+    #[test]
+    fn focus_mode_moves_between_tasks_and_keeps_the_selection() {
+        let mut app = app();
+        app.add_task("first").unwrap();
+        app.add_task("second").unwrap();
+        app.state.select(Some(0));
+        let press = |app: &mut App, c| {
+            app.handle_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+                .unwrap()
+        };
+
+        press(&mut app, 'z');
+        assert!(app.mode == Mode::Focus);
+        press(&mut app, 'j');
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("second") && !text.contains("first"));
+
+        app.handle_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))
+            .unwrap();
+        assert!(app.mode == Mode::Normal);
+        assert_eq!(app.selected_item().unwrap().description, "second");
+    }
+
+    #[test]
+    fn each_list_keeps_its_own_tasks_and_is_named_in_the_title() {
+        let data_dir = test_dir("lists");
+        let mut app = App::new(
+            db::open(&data_dir.join("tasks.db")).unwrap(),
+            data_dir.clone(),
+        )
+        .unwrap();
+        app.add_task("pay rent").unwrap();
+
+        app.execute_command(Command::List("work".to_string()))
+            .unwrap();
+        assert!(app.items.is_empty());
+        app.add_task("send report").unwrap();
+        assert!(data_dir.join("tasks-work.db").exists());
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let screen: String = buf.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("Task Manager · work"));
+
+        app.show_lists().unwrap();
+        assert_eq!(app.list_names, ["default", "work"]);
+        assert_eq!(app.lists_state.selected(), Some(1));
+        for code in [KeyCode::Char('k'), KeyCode::Enter] {
+            app.handle_event(KeyEvent::new(code, KeyModifiers::NONE))
+                .unwrap();
+        }
+        assert!(app.mode == Mode::Normal && app.active_list.is_none());
+        assert_eq!(app.items.len(), 1);
+        assert_eq!(app.items[0].description, "pay rent");
+        drop(app);
+        fs::remove_dir_all(&data_dir).unwrap();
+
+        let mut app = self::app();
+        app.execute_command(Command::List("work".to_string()))
+            .unwrap();
+        assert!(matches!(app.status, Some(Status::Error(_))));
+    }
+
+    #[test]
+    fn view_settings_are_saved_in_each_lists_database() {
+        let data_dir = test_dir("settings");
+        fs::write(data_dir.join(CONFIG_FILE), "default_sort = \"alpha\"\n").unwrap();
+        let path = data_dir.join("tasks.db");
+        let mut app = App::new(db::open(&path).unwrap(), data_dir.clone()).unwrap();
+        assert!(app.sort_mode == SortMode::Alphabetical && !app.hide_done);
+        app.toggle_hide_done().unwrap();
+        app.toggle_priority_filter(Priority::High).unwrap();
+        app.cycle_theme().unwrap();
+
+        // A new list starts from the config, not from the list it was opened from, but
+        // shares the theme.
+        app.execute_command(Command::List("work".to_string()))
+            .unwrap();
+        assert!(app.sort_mode == SortMode::Alphabetical && !app.hide_done);
+        assert!(app.priority_filter.is_none());
+        assert!(app.theme_name == ThemeName::Light);
+        app.execute_command(Command::Sort(SortMode::Priority))
+            .unwrap();
+        app.execute_command(Command::List("default".to_string()))
+            .unwrap();
+        assert!(app.sort_mode == SortMode::Alphabetical && app.hide_done);
+        drop(app);
+
+        // The sort was never changed in the default list, so the config still picks it.
+        fs::write(data_dir.join(CONFIG_FILE), "default_sort = \"id\"\n").unwrap();
+        let app = App::new(db::open(&path).unwrap(), data_dir.clone()).unwrap();
+        assert!(app.sort_mode == SortMode::Id);
+        assert!(app.hide_done);
+        assert!(app.priority_filter == Some(Priority::High));
+        assert_eq!(app.tag_filter, None);
+        assert!(app.theme_name == ThemeName::Light);
+        drop(app);
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn a_draft_left_by_a_crash_is_offered_and_cleared_once_saved() {
+        let data_dir = test_dir("draft");
+        let draft = data_dir.join(DRAFT_FILE);
+        fs::write(&draft, "plan the trip to").unwrap();
+        let mut app = App::new(Connection::open_in_memory().unwrap(), data_dir.clone()).unwrap();
+        let press = |app: &mut App, code| {
+            app.handle_event(KeyEvent::new(code, KeyModifiers::NONE))
+                .unwrap();
+            app.save_draft().unwrap();
+        };
+
+        app.offer_draft();
+        assert!(app.mode == Mode::ConfirmRestore);
+        press(&mut app, KeyCode::Char('y'));
+        assert!(app.mode == Mode::Input);
+        for c in " Rome".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert_eq!(fs::read_to_string(&draft).unwrap(), "plan the trip to Rome");
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.items[0].description, "plan the trip to Rome");
+        assert!(!draft.exists());
+
+        fs::write(&draft, "call").unwrap();
+        app.offer_draft();
+        press(&mut app, KeyCode::Char('n'));
+        assert!(app.mode == Mode::Normal && !draft.exists());
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn startup_applies_the_configured_sort_and_filter() {
+        let data_dir = test_dir("startup");
+        let database = data_dir.join("tasks.db");
+
+        let mut app = App::new(Connection::open(&database).unwrap(), data_dir.clone()).unwrap();
+        app.add_task("carrots").unwrap();
+        app.add_task("apples").unwrap();
+        app.add_task("bread").unwrap();
+        let done = app
+            .items
+            .iter()
+            .position(|item| item.description == "bread");
+        app.toggle_task(done.unwrap()).unwrap();
+        app.select_id(app.items[done.unwrap()].id);
+        app.save_selection().unwrap();
+        drop(app);
+        fs::write(
+            data_dir.join(CONFIG_FILE),
+            "default_sort = \"alpha\"\ndefault_filter = \"hide_done\"\n",
+        )
+        .unwrap();
+
+        let app = App::new(Connection::open(&database).unwrap(), data_dir.clone()).unwrap();
+        assert_eq!(app.sort_mode, SortMode::Alphabetical);
+        let visible: Vec<&str> = app
+            .visible_indices()
+            .into_iter()
+            .map(|index| app.items[index].description.as_str())
+            .collect();
+        assert_eq!(visible, ["apples", "carrots"]);
+        assert_eq!(app.selected_item().unwrap().description, "apples");
+        drop(app);
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+
+    #[test]
+    fn priority_filter_stacks_with_the_done_filter() {
+        let mut app = app();
+        for (description, priority) in [
+            ("ship release", Priority::High),
+            ("water plants", Priority::Normal),
+            ("fix outage", Priority::High),
+        ] {
+            let id = app.add_task(description).unwrap();
+            db::set_priority(&app.connection, id, priority, Utc::now()).unwrap();
+        }
+        app.load_tasks().unwrap();
+        db::set_done(&app.connection, app.items[0].id, true, Utc::now()).unwrap();
+        app.load_tasks().unwrap();
+
+        app.toggle_priority_filter(Priority::High).unwrap();
+        app.toggle_hide_done().unwrap();
+        assert_eq!(app.visible_indices(), [2]);
+        assert_eq!(app.selected_item().unwrap().description, "fix outage");
+
+        app.toggle_priority_filter(Priority::High).unwrap();
+        assert_eq!(app.visible_indices(), [1, 2]);
+    }
+
+    #[test]
+    fn session_completions_leave_out_tasks_toggled_back() {
+        let mut app = app();
+        app.add_task("write report").unwrap();
+        app.add_task("call mom").unwrap();
+        app.add_task("buy milk").unwrap();
+
+        app.toggle_task(0).unwrap();
+        app.toggle_task(1).unwrap();
+        app.toggle_task(1).unwrap();
+
+        let completed: Vec<String> = app
+            .session_completions()
+            .unwrap()
+            .into_iter()
+            .map(|item| item.description)
+            .collect();
+        assert_eq!(completed, ["write report"]);
+    }
+
+    #[test]
+    fn status_cycles_through_doing_and_undo_restores_it() {
+        let mut app = app();
+        app.add_task("write report").unwrap();
+        app.select_row(0);
+
+        app.change_selected_status(TaskStatus::next).unwrap();
+        assert_eq!(app.items[0].status, TaskStatus::Doing);
+        assert!(!app.items[0].is_done);
+
+        app.toggle_selected().unwrap();
+        assert_eq!(app.items[0].status, TaskStatus::Done);
+        assert!(stored_done(&app, app.items[0].id));
+
+        app.undo().unwrap();
+        assert_eq!(app.items[0].status, TaskStatus::Doing);
+        assert!(!stored_done(&app, app.items[0].id));
+
+        app.change_selected_status(TaskStatus::previous).unwrap();
+        app.change_selected_status(TaskStatus::previous).unwrap();
+        assert_eq!(app.items[0].status, TaskStatus::Done);
+        assert!(app.items[0].completed_at.is_some());
+    }
+
+    #[test]
+    fn undo_restores_an_edited_description_and_notes() {
+        let mut app = app();
+        app.add_task("project: home fix sink #diy").unwrap();
+        app.update_task(0, "fix the kitchen sink").unwrap();
+        app.set_notes(0, "call plumber".to_string()).unwrap();
+
+        app.undo().unwrap();
+        assert_eq!(app.items[0].notes, "");
+        assert_eq!(app.items[0].description, "fix the kitchen sink");
+
+        app.undo().unwrap();
+        let item = &app.items[0];
+        assert_eq!(item.description, "fix sink");
+        assert_eq!(item.tags, ["#diy"]);
+        assert_eq!(item.category.as_deref(), Some("home"));
+    }
+
+    #[test]
+    fn line_numbers_fit_the_largest_visible_index() {
+        let mut app = app();
+        assert_eq!(app.line_number_width(), 0);
+        app.toggle_line_numbers();
+        for n in 1..=10 {
+            app.add_task(&format!("task {}", n)).unwrap();
+        }
+        assert_eq!(app.line_number_width(), 3);
+
+        app.search_buffer.set("task 1".to_string());
+        assert_eq!(app.line_number_width(), 2);
+    }
+
+    #[test]
+    fn search_ranks_the_best_fuzzy_matches_first() {
+        let mut app = app();
+        app.add_task("task manager").unwrap();
+        app.add_task("water plants").unwrap();
+        app.add_task("mgr report").unwrap();
+        app.search_buffer.set("mgr".to_string());
+
+        let shown: Vec<&str> = app
+            .visible_indices()
+            .into_iter()
+            .map(|index| app.items[index].description.as_str())
+            .collect();
+
+        assert_eq!(shown, ["mgr report", "task manager"]);
+    }
+
+    #[test]
+    fn done_last_sort_sinks_toggled_tasks_and_follows_the_selection() {
+        let mut app = app();
+        for description in ["first", "second", "third"] {
+            app.add_task(description).unwrap();
+        }
+        app.set_sort_mode(SortMode::DoneLast).unwrap();
+        app.state.select(Some(0));
+
+        app.toggle_selected().unwrap();
+        let order: Vec<&str> = app
+            .items
+            .iter()
+            .map(|item| item.description.as_str())
+            .collect();
+        assert_eq!(order, ["second", "third", "first"]);
+        assert_eq!(app.selected_item().unwrap().description, "first");
+
+        app.state.select(Some(1));
+        app.toggle_selected().unwrap();
+        app.toggle_selected().unwrap();
+        let order: Vec<&str> = app
+            .items
+            .iter()
+            .map(|item| item.description.as_str())
+            .collect();
+        assert_eq!(order, ["second", "third", "first"]);
+        assert_eq!(app.selected_item().unwrap().description, "third");
+    }
+
+    #[test]
+    fn opening_a_task_without_a_link_says_so() {
+        let mut app = app();
+        app.add_task("call the bank").unwrap();
+        app.state.select(Some(0));
+
+        app.handle_event(KeyEvent::new(KeyCode::Char('O'), KeyModifiers::SHIFT))
+            .unwrap();
+
+        assert!(
+            matches!(&app.status, Some(Status::Error(message)) if message == "No link in this task")
+        );
+    }
+
+    #[test]
+    fn stats_chart_has_a_bar_for_each_day_in_range() {
+        let mut app = app();
+        app.add_task("write report").unwrap();
+        app.toggle_task(0).unwrap();
+        app.chart_days = 7;
+
+        app.show_stats().unwrap();
+        assert_eq!(app.completions_per_day.len(), 7);
+        assert_eq!(app.completions_per_day[6], (Local::now().date_naive(), 1));
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let screen: String = buf.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("Completed per day, last 7 days"));
+
+        app.chart_days = 0;
+        app.show_stats().unwrap();
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let screen: String = buf.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(!screen.contains("Completed per day"));
+    }
+
+    #[test]
+    fn snoozing_pushes_the_due_date_back_a_day_or_a_week() {
+        let mut app = app();
+        app.add_task("call the bank").unwrap();
+        app.state.select(Some(0));
+        let tomorrow = Local::now().date_naive().succ_opt().unwrap();
+
+        app.handle_event(KeyEvent::new(KeyCode::Char('>'), KeyModifiers::SHIFT))
+            .unwrap();
+        assert_eq!(app.items[0].due_date, Some(tomorrow));
+
+        let time = NaiveTime::from_hms_opt(9, 30, 0).unwrap();
+        app.set_due_date(0, Some(tomorrow), Some(time)).unwrap();
+        app.handle_event(KeyEvent::new(
+            KeyCode::Char('>'),
+            KeyModifiers::ALT | KeyModifiers::SHIFT,
+        ))
+        .unwrap();
+        let next_week = tomorrow + Days::new(7);
+        assert_eq!(
+            (app.items[0].due_date, app.items[0].due_time),
+            (Some(next_week), Some(time))
+        );
+        let stored = db::load_tasks(&app.connection, None, SortMode::Id).unwrap();
+        assert_eq!(stored[0].due_date, Some(next_week));
+        assert!(matches!(&app.status, Some(Status::Info(message)) if message.ends_with("09:30")));
+    }
+
+    #[test]
+    fn pinned_tasks_sit_above_every_heading_until_unpinned() {
+        let mut app = app();
+        app.add_task("loose").unwrap();
+        app.add_task("project: work report").unwrap();
+        app.add_task("project: work review").unwrap();
+        app.state.select(Some(2));
+
+        app.handle_event(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(app.selected_item().unwrap().description, "loose");
+        assert_eq!(app.state.selected(), Some(0));
+        assert_eq!(
+            app.list_rows(),
+            [
+                ListRow::Task(0),
+                ListRow::Header(Some("work".to_string())),
+                ListRow::Task(1),
+                ListRow::Task(2),
+            ]
+        );
+        let stored = db::load_tasks(&app.connection, None, SortMode::Id).unwrap();
+        assert!(stored[0].pinned);
+
+        app.handle_event(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(app.state.selected(), Some(2));
+        assert!(matches!(&app.status, Some(Status::Info(message)) if message == "Unpinned"));
+    }
+
+    #[test]
+    fn a_blocked_task_cannot_be_done_until_its_blocker_is() {
+        let mut app = app();
+        app.add_task("pack boxes").unwrap();
+        app.add_task("book van").unwrap();
+        app.state.select(Some(1));
+        app.execute_command(Command::Block(1)).unwrap();
+        assert!(app.items[1].blocked);
+
+        let space = KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE);
+        app.handle_event(space).unwrap();
+        assert!(!app.items[1].is_done);
+        assert!(
+            matches!(&app.status, Some(Status::Error(message)) if message.contains("pack boxes"))
+        );
+
+        app.state.select(Some(0));
+        assert!(app.execute_command(Command::Block(2)).is_err());
+        app.handle_event(space).unwrap();
+        assert!(!app.items[1].blocked);
+        app.state.select(Some(1));
+        app.handle_event(space).unwrap();
+        assert!(app.items[1].is_done);
+
+        app.toggle_task(0).unwrap();
+        let stored = db::load_tasks(&app.connection, None, SortMode::Id).unwrap();
+        assert!(stored[1].blocked);
+    }
+
+    #[test]
+    fn collapsing_done_tasks_adds_a_summary_row_that_expands_on_enter() {
+        let mut app = app();
+        for description in ["first", "second", "third"] {
+            app.add_task(description).unwrap();
+        }
+        app.toggle_task(0).unwrap();
+        app.toggle_task(2).unwrap();
+        app.state.select(Some(1));
+
+        app.handle_event(KeyEvent::new(KeyCode::Char('Z'), KeyModifiers::SHIFT))
+            .unwrap();
+        assert_eq!(app.list_rows(), [ListRow::Task(0), ListRow::Completed(2)]);
+        assert_eq!(app.selected_item().unwrap().description, "second");
+
+        app.select_last();
+        assert!(app.summary_selected());
+        assert!(app.selected_item().is_none());
+        let mut buf = Buffer::empty(Rect::new(0, 0, 80, 24));
+        (&mut app).render(buf.area, &mut buf);
+        let screen: String = buf.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("▸ 2 completed"));
+
+        app.handle_event(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE))
+            .unwrap();
+        assert_eq!(app.list_rows().len(), 3);
+        assert_eq!(app.selected_item().unwrap().description, "first");
+    }
+
+    #[test]
+    fn search_covers_tags_and_notes_after_descriptions() {
+        let mut app = app();
+        app.add_task("send invoice #billing").unwrap();
+        app.add_task("billing run").unwrap();
+        app.add_task("call Sam").unwrap();
+        app.add_task("water plants").unwrap();
+        let call = app
+            .items
+            .iter()
+            .position(|item| item.description == "call Sam")
+            .unwrap();
+        app.set_notes(call, "Re: BILLING question".to_string())
+            .unwrap();
+        app.search_buffer.set("billing".to_string());
+
+        let shown: Vec<(&str, SearchField)> = app
+            .visible_indices()
+            .into_iter()
+            .map(|index| {
+                let item = &app.items[index];
+                (
+                    item.description.as_str(),
+                    search_task(item, "billing").unwrap().0,
+                )
+            })
+            .collect();
+
+        assert!(
+            shown
+                == [
+                    ("billing run", SearchField::Description),
+                    ("send invoice", SearchField::Tag),
+                    ("call Sam", SearchField::Notes),
+                ]
+        );
+    }
+
+    #[test]
+    fn turning_pages_selects_the_first_task_of_the_page() {
+        let mut app = app();
+        for n in 1..=7 {
+            app.add_task(&format!("task {}", n)).unwrap();
+        }
+        app.list_area = Rect::new(0, 0, 40, 3);
+        app.paged = true;
+        app.select_row(1);
+
+        app.turn_page(true);
+        assert_eq!(app.state.selected(), Some(3));
+        app.turn_page(true);
+        assert_eq!(app.state.selected(), Some(6));
+        app.turn_page(true);
+        assert_eq!(app.state.selected(), Some(6));
+
+        let rows = app.list_rows();
+        assert_eq!((app.current_page(&rows), app.page_count(&rows)), (2, 3));
+
+        app.turn_page(false);
+        assert_eq!(app.state.selected(), Some(3));
+    }
+
+    #[test]
+    fn undo_restores_a_deleted_task() {
+        let mut app = app();
+        let id = app.add_task("keep me #tag").unwrap();
+        app.delete_task(0).unwrap();
+
+        app.undo().unwrap();
+
+        assert_eq!(app.items.len(), 1);
+        assert_eq!(app.items[0].id, id);
+        assert_eq!(app.items[0].tags, ["#tag"]);
+    }
+
+    /// The fields undo and redo touch, for the in-memory items and the stored ones.
+    fn snapshot(app: &App) -> (Vec<String>, Vec<String>) {
+        let describe = |item: &TodoItem| {
+            format!(
+                "{} {} {:?} {:?} {:?} {:?} {}",
+                item.id,
+                item.description,
+                item.tags,
+                item.category,
+                item.status,
+                item.notes,
+                item.archived
+            )
+        };
+        let stored = db::load_tasks(&app.connection, None, SortMode::Id).unwrap();
+        (
+            app.items.iter().map(describe).collect(),
+            stored.iter().map(describe).collect(),
+        )
+    }
+
+    #[test]
+    fn redo_reapplies_each_kind_of_undone_change() {
+        let mut app = app();
+        app.add_task("water plants").unwrap();
+        app.add_task("call mom").unwrap();
+        let changes: [fn(&mut App); 6] = [
+            |app| app.toggle_task(0).unwrap(),
+            |app| {
+                app.update_task(0, "project: home water plants #weekly")
+                    .unwrap()
+            },
+            |app| app.set_notes(0, "the fern too".to_string()).unwrap(),
+            |app| app.set_status(1, TaskStatus::Doing).unwrap(),
+            |app| app.delete_task(1).unwrap(),
+            |app| {
+                app.add_task("buy milk").unwrap();
+            },
+        ];
+
+        for change in changes {
+            let before = snapshot(&app);
+            change(&mut app);
+            let after = snapshot(&app);
+
+            app.undo().unwrap();
+            assert_eq!(snapshot(&app), before);
+            app.redo().unwrap();
+            assert_eq!(snapshot(&app), after);
+        }
+    }
+
+    #[test]
+    fn a_new_change_clears_the_redo_stack() {
+        let mut app = app();
+        app.add_task("first").unwrap();
+        app.toggle_task(0).unwrap();
+        app.undo().unwrap();
+
+        app.add_task("second").unwrap();
+        app.redo().unwrap();
+
+        assert!(!app.items[0].is_done);
+        assert!(matches!(app.status, Some(Status::Error(_))));
+    }
+
+    #[test]
+    fn an_import_clears_the_redo_stack() {
+        let mut app = app();
+        app.add_task("first").unwrap();
+        app.toggle_task(0).unwrap();
+        app.undo().unwrap();
+        let tasks: Vec<ImportedTask> =
+            serde_json::from_str(r#"[{"description": "imported"}]"#).unwrap();
+
+        app.import_tasks(&tasks, ImportMode::Merge).unwrap();
+        app.redo().unwrap();
+
+        assert!(app.items.iter().all(|item| !item.is_done));
+        assert!(matches!(app.status, Some(Status::Error(_))));
+    }
+
+    #[test]
+    fn redo_completes_a_reopened_recurring_task_again() {
+        let mut app = app();
+        app.add_task("stretch").unwrap();
+        app.set_recurrence(0, Recurrence::Daily).unwrap();
+        app.toggle_task(0).unwrap();
+        app.undo().unwrap();
+
+        app.redo().unwrap();
 
-        footer.render(area, buf);
+        let stored = db::load_tasks(&app.connection, None, SortMode::Id).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored[0].is_done && stored[0].archived);
+        assert!(!stored[1].is_done && stored[1].recurrence == Recurrence::Daily);
     }
 }
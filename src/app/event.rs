@@ -0,0 +1,402 @@
+use color_eyre::Result;
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::{Position, Rect};
+
+use super::{App, ListRow, Mode, Status, ui::HIGHLIGHT_SYMBOL};
+use crate::{
+    command::Command,
+    db::ImportMode,
+    export,
+    keymap::KeyAction,
+    model::{Priority, TaskStatus},
+};
+
+/// Upper bound on a numeric prefix so runaway digits can't overflow.
+const MAX_COUNT: usize = 9999;
+
+impl App {
+    /// Mouse input only applies over the list. The wheel moves the selection one task per
+    /// tick; a left click on a row selects it and a click on its checkbox also toggles it.
+    pub(super) fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        let area = self.list_area;
+        if !matches!(self.mode, Mode::Normal | Mode::Visual)
+            || !area.contains(Position::new(mouse.column, mouse.row))
+        {
+            return Ok(());
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown => self.select_next(),
+            MouseEventKind::ScrollUp => self.select_previous(),
+            MouseEventKind::Down(MouseButton::Left) if self.mode == Mode::Normal => {
+                self.click_row(area, mouse)?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn click_row(&mut self, area: Rect, mouse: MouseEvent) -> Result<()> {
+        let line = self.list_offset + (mouse.row - area.y) as usize;
+        let row = match self.list_rows().get(line) {
+            Some(&ListRow::Task(row)) => row,
+            Some(ListRow::Completed(_)) => {
+                self.state.select(Some(self.visible_indices().len()));
+                self.toggle_completed_collapsed();
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        self.status = None;
+        self.state.select(Some(row));
+
+        // Rows are indented by the highlight symbol, the line numbers when shown, while
+        // marking the mark column, and under their parent for subtasks.
+        let mut checkbox_start =
+            area.x + HIGHLIGHT_SYMBOL.chars().count() as u16 + self.line_number_width() as u16;
+        if self.mode == Mode::Visual || !self.marked.is_empty() {
+            checkbox_start += 2;
+        }
+        if self.subtask_rows(&self.visible_indices())[row] {
+            checkbox_start += 2;
+        }
+        if (checkbox_start..checkbox_start + 3).contains(&mouse.column) {
+            self.toggle_selected()?;
+        }
+        Ok(())
+    }
+
+    /// Inserts pasted text at the cursor of whichever prompt is open. Line breaks are kept
+    /// in notes and become spaces everywhere else, so a pasted newline never submits.
+    pub(super) fn handle_paste(&mut self, text: &str) -> Result<()> {
+        self.status = None;
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self.mode {
+            Mode::Notes => self.input_buffer.insert_str(&text),
+            Mode::Input | Mode::Edit | Mode::DueDate | Mode::Command => {
+                self.input_buffer.insert_str(&text.replace('\n', " "))
+            }
+            Mode::Search => {
+                self.search_buffer.insert_str(&text.replace('\n', " "));
+                self.sync_window();
+                self.reset_selection();
+            }
+            _ => {}
+        }
+        self.update_tag_suggestions()
+    }
+
+    pub(super) fn handle_event(&mut self, key: KeyEvent) -> Result<()> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(());
+        }
+
+        self.status = None;
+        if !self.reminders.is_empty() {
+            self.reminders.clear();
+            return Ok(());
+        }
+
+        match self.mode {
+            Mode::Normal => {
+                let after_g = std::mem::take(&mut self.pending_g);
+                if let KeyCode::Char(c @ '0'..='9') = key.code
+                    && !key
+                        .modifiers
+                        .intersects(KeyModifiers::ALT | KeyModifiers::CONTROL)
+                    && (c != '0' || self.pending_count.is_some())
+                {
+                    let count = self.pending_count.unwrap_or(0) * 10 + c as usize - '0' as usize;
+                    self.pending_count = Some(count.min(MAX_COUNT));
+                    return Ok(());
+                }
+                if key.code == KeyCode::Esc && (self.pending_count.take().is_some() || after_g) {
+                    return Ok(());
+                }
+                if self.keymap.action(key) == Some(KeyAction::First) && !after_g {
+                    self.pending_g = true;
+                    return Ok(());
+                }
+                let count = self.pending_count.take();
+                self.handle_normal_key(key, count)?;
+            }
+            Mode::Help => match key.code {
+                KeyCode::Char('?') | KeyCode::Char('q') | KeyCode::Esc => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::Focus => match (key.code, self.keymap.action(key)) {
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) | (_, Some(KeyAction::Focus)) => {
+                    self.mode = Mode::Normal
+                }
+                (_, Some(KeyAction::Down)) => self.select_next(),
+                (_, Some(KeyAction::Up)) => self.select_previous(),
+                (_, Some(KeyAction::Toggle)) => self.toggle_selected()?,
+                _ => {}
+            },
+            Mode::Activity => match (key.code, self.keymap.action(key)) {
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) | (_, Some(KeyAction::Activity)) => {
+                    self.mode = Mode::Normal
+                }
+                (_, Some(KeyAction::Down)) => self.activity_state.select_next(),
+                (_, Some(KeyAction::Up)) => self.activity_state.select_previous(),
+                (_, Some(KeyAction::PageDown)) => self.activity_state.scroll_down_by(10),
+                (_, Some(KeyAction::PageUp)) => self.activity_state.scroll_up_by(10),
+                (_, Some(KeyAction::First)) => self.activity_state.select_first(),
+                (_, Some(KeyAction::Last)) => self.activity_state.select_last(),
+                _ => {}
+            },
+            Mode::Lists => match (key.code, self.keymap.action(key)) {
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) | (_, Some(KeyAction::Lists)) => {
+                    self.mode = Mode::Normal
+                }
+                (KeyCode::Enter, _) => {
+                    if let Some(name) = self
+                        .lists_state
+                        .selected()
+                        .and_then(|row| self.list_names.get(row))
+                    {
+                        self.switch_list(&name.clone())?;
+                    }
+                }
+                (_, Some(KeyAction::Down)) => self.lists_state.select_next(),
+                (_, Some(KeyAction::Up)) => self.lists_state.select_previous(),
+                _ => {}
+            },
+            Mode::Stats => match key.code {
+                KeyCode::Char('S') | KeyCode::Char('q') | KeyCode::Esc => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::ConfirmRestore => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    self.input_buffer.set(self.draft.clone());
+                    self.mode = Mode::Input;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::ConfirmDiscard { quit, editing } => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    self.cancel_input();
+                    self.should_exit = quit;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.mode = if editing { Mode::Edit } else { Mode::Input };
+                }
+                _ => {}
+            },
+            Mode::ConfirmClearCompleted => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    self.clear_completed()?;
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::ConfirmDelete => match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    if self.marked.is_empty() {
+                        self.delete_selected()?;
+                    } else {
+                        self.delete_marked()?;
+                    }
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.delete_count = 1;
+                    self.mode = if self.marked.is_empty() {
+                        Mode::Normal
+                    } else {
+                        Mode::Visual
+                    };
+                }
+                _ => {}
+            },
+            Mode::Visual => match (key.code, self.keymap.action(key)) {
+                (KeyCode::Char(' '), _) => self.toggle_mark(),
+                (KeyCode::Enter, _) => self.toggle_marked()?,
+                (KeyCode::Esc, _) | (_, Some(KeyAction::Visual)) => {
+                    self.marked.clear();
+                    self.mode = Mode::Normal;
+                }
+                (_, Some(KeyAction::Down)) => self.select_next(),
+                (_, Some(KeyAction::Up)) => self.select_previous(),
+                (_, Some(KeyAction::First)) => self.select_first(),
+                (_, Some(KeyAction::Last)) => self.select_last(),
+                (_, Some(KeyAction::Delete)) if !self.marked.is_empty() => {
+                    self.mode = Mode::ConfirmDelete
+                }
+                _ => {}
+            },
+            Mode::ConfirmImport => match key.code {
+                KeyCode::Char('m') => self.import_json(ImportMode::Merge)?,
+                KeyCode::Char('r') => self.import_json(ImportMode::Replace)?,
+                KeyCode::Esc | KeyCode::Char('n') => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::Search => match key.code {
+                KeyCode::Enter => self.mode = Mode::Normal,
+                KeyCode::Esc => {
+                    self.clear_search();
+                    self.mode = Mode::Normal;
+                }
+                _ => {
+                    if self.search_buffer.handle_key(key) {
+                        self.sync_window();
+                        self.reset_selection();
+                    }
+                }
+            },
+            Mode::Notes => match key.code {
+                KeyCode::Enter
+                    if key
+                        .modifiers
+                        .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+                {
+                    self.input_buffer.insert('\n')
+                }
+                KeyCode::Char('j') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.input_buffer.insert('\n')
+                }
+                KeyCode::Enter => self.submit_notes()?,
+                KeyCode::Esc => {
+                    self.input_buffer.clear();
+                    self.mode = Mode::Normal;
+                }
+                _ => {
+                    self.input_buffer.handle_key(key);
+                }
+            },
+            Mode::Command => match key.code {
+                KeyCode::Enter => {
+                    self.mode = Mode::Normal;
+                    let input = self.input_buffer.take();
+                    match Command::parse(&input) {
+                        Ok(command) => self.execute_command(command)?,
+                        Err(err) => self.status = Some(Status::Error(err.to_string())),
+                    }
+                }
+                KeyCode::Esc => {
+                    self.input_buffer.clear();
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Backspace if self.input_buffer.is_empty() => self.mode = Mode::Normal,
+                _ => {
+                    self.input_buffer.handle_key(key);
+                }
+            },
+            Mode::Input | Mode::Edit | Mode::DueDate => {
+                let suggestions = self.tag_suggestions.len();
+                match key.code {
+                    KeyCode::Tab | KeyCode::Enter if suggestions > 0 => {
+                        self.accept_tag_suggestion()
+                    }
+                    KeyCode::Down if suggestions > 0 => {
+                        self.suggestion = (self.suggestion + 1) % suggestions;
+                    }
+                    KeyCode::Up if suggestions > 0 => {
+                        self.suggestion = (self.suggestion + suggestions - 1) % suggestions;
+                    }
+                    KeyCode::Enter => self.submit_input()?,
+                    KeyCode::Esc => self.leave_input(false),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.leave_input(true)
+                    }
+                    _ => {
+                        self.input_buffer.handle_key(key);
+                    }
+                }
+                self.update_tag_suggestions()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a key in normal mode through the keymap. `count` is the numeric prefix typed
+    /// before it, and `First` here is the second half of `gg`.
+    fn handle_normal_key(&mut self, key: KeyEvent, count: Option<usize>) -> Result<()> {
+        let repeat = count.unwrap_or(1);
+        let Some(action) = self.keymap.action(key) else {
+            return Ok(());
+        };
+        match action {
+            KeyAction::Quit => self.should_exit = true,
+            KeyAction::ArchiveView => self.toggle_archive_view()?,
+            KeyAction::Archive => self.archive_selected()?,
+            KeyAction::Add => {
+                self.mode = Mode::Input;
+                self.subtask_parent = None;
+                self.input_buffer.clear();
+            }
+            KeyAction::AddSubtask => self.start_subtask(),
+            KeyAction::Edit => self.edit_selected(),
+            KeyAction::Duplicate => self.duplicate_selected()?,
+            KeyAction::Yank => self.yank_selected(),
+            KeyAction::OpenUrl => self.open_selected_url(),
+            KeyAction::MoveDown => self.move_selected_down()?,
+            KeyAction::MoveUp => self.move_selected_up()?,
+            KeyAction::MoveTop => self.move_selected_to_end(true)?,
+            KeyAction::MoveBottom => self.move_selected_to_end(false)?,
+            KeyAction::PageDown => self.select_page_down(),
+            KeyAction::PageUp => self.select_page_up(),
+            KeyAction::Paged => self.toggle_paged(),
+            KeyAction::NextPage => self.turn_page(true),
+            KeyAction::PreviousPage => self.turn_page(false),
+            KeyAction::Down => self.select_next_by(repeat),
+            KeyAction::Up => self.select_previous_by(repeat),
+            KeyAction::First => self.select_position(count.map_or(0, |count| count - 1)),
+            KeyAction::Last => match count {
+                Some(count) => self.select_position(count - 1),
+                None => self.select_last(),
+            },
+            KeyAction::NextIncomplete => self.select_next_incomplete(),
+            KeyAction::PreviousIncomplete => self.select_prev_incomplete(),
+            KeyAction::Toggle if self.summary_selected() => self.toggle_completed_collapsed(),
+            KeyAction::Toggle => self.toggle_selected()?,
+            KeyAction::ToggleAll => self.toggle_all()?,
+            KeyAction::StatusNext => self.change_selected_status(TaskStatus::next)?,
+            KeyAction::StatusPrevious => self.change_selected_status(TaskStatus::previous)?,
+            KeyAction::Delete => self.confirm_delete_selected(repeat),
+            KeyAction::ClearCompleted => self.confirm_clear_completed(),
+            KeyAction::RaisePriority => self.change_selected_priority(Priority::raised)?,
+            KeyAction::LowerPriority => self.change_selected_priority(Priority::lowered)?,
+            KeyAction::Sort => self.cycle_sort_mode()?,
+            KeyAction::Theme => self.cycle_theme()?,
+            KeyAction::DueDate => self.edit_selected_due_date(),
+            KeyAction::SnoozeDay => self.snooze_selected(1)?,
+            KeyAction::SnoozeWeek => self.snooze_selected(7)?,
+            KeyAction::Recurrence => self.cycle_selected_recurrence()?,
+            KeyAction::Color => self.cycle_selected_color()?,
+            KeyAction::Pin => self.toggle_selected_pin()?,
+            KeyAction::Focus => self.enter_focus(),
+            KeyAction::Detail => self.show_detail = !self.show_detail,
+            KeyAction::HideDone => self.toggle_hide_done()?,
+            KeyAction::CollapseDone => self.toggle_completed_collapsed(),
+            KeyAction::LineNumbers => self.toggle_line_numbers(),
+            KeyAction::Today => self.toggle_today_view(),
+            KeyAction::FilterHigh => self.toggle_priority_filter(Priority::High)?,
+            KeyAction::FilterNormal => self.toggle_priority_filter(Priority::Normal)?,
+            KeyAction::FilterLow => self.toggle_priority_filter(Priority::Low)?,
+            KeyAction::Notes => self.edit_selected_notes(),
+            KeyAction::Editor => self.request_editor(),
+            KeyAction::Undo => self.undo()?,
+            KeyAction::Redo => self.redo()?,
+            KeyAction::Search => self.mode = Mode::Search,
+            KeyAction::ClearSearch => self.clear_search(),
+            KeyAction::Help => self.mode = Mode::Help,
+            KeyAction::CommandLine => self.mode = Mode::Command,
+            KeyAction::Stats => self.show_stats()?,
+            KeyAction::Activity => self.show_activity()?,
+            KeyAction::Lists => self.show_lists()?,
+            KeyAction::TagFilter => self.cycle_tag_filter()?,
+            KeyAction::Visual => self.mode = Mode::Visual,
+            KeyAction::ExportJson => self.export(export::export_json),
+            KeyAction::ExportMarkdown => self.export(export::export_markdown),
+            KeyAction::ExportCsv => self.export(export::export_csv),
+            KeyAction::Import => self.mode = Mode::ConfirmImport,
+        }
+        Ok(())
+    }
+}
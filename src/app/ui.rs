@@ -0,0 +1,1343 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use ratatui::{
+    prelude::*,
+    widgets::{
+        Bar, BarChart, Block, Borders, Clear, Gauge, LineGauge, List, ListItem, ListState,
+        Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap,
+    },
+};
+
+use super::{
+    App, ListRow, Mode, REMINDER_WINDOW, SAVED_INDICATOR, SearchField, Status, search_task,
+};
+use crate::{
+    config::Theme,
+    fuzzy::fuzzy_match,
+    input::TextInput,
+    model::{Activity, DATE_FORMAT, DUE_TIME_FORMAT, Priority, Recurrence, TaskColor, TaskStatus},
+};
+
+pub(super) const HIGHLIGHT_SYMBOL: &str = "▶ ";
+/// Lines kept visible above and below the selection while scrolling the list.
+const SCROLL_PADDING: usize = 1;
+/// Lines taken by the completion chart in the statistics overlay: its title, the bars and
+/// the day labels.
+const CHART_HEIGHT: u16 = 8;
+
+/// The smallest terminal the layout fits in: one list line between the list borders, the
+/// progress bar and the footer.
+const MIN_WIDTH: u16 = 20;
+const MIN_HEIGHT: u16 = 7;
+
+/// Every keybinding grouped by category, rendered by the help screen.
+const KEYBINDINGS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "Navigation",
+        &[
+            ("j / ↓", "Move down"),
+            ("k / ↑", "Move up"),
+            ("gg / <n>gg", "Jump to first task / task <n>"),
+            ("G / <n>G", "Jump to last task / task <n>"),
+            ("] / [", "Next / previous incomplete task"),
+            (
+                "<count> j / k",
+                "Move down / up <count> tasks (also <count> d)",
+            ),
+            ("PgDn / Ctrl+d", "Page down"),
+            ("PgUp / Ctrl+u", "Page up"),
+            ("P", "Toggle paged view"),
+            ("Ctrl+f / Ctrl+b", "Next / previous page"),
+        ],
+    ),
+    (
+        "Tasks",
+        &[
+            ("a", "Add new task"),
+            ("o", "Add a subtask to the selected task"),
+            ("e", "Edit selected task"),
+            ("y", "Duplicate selected task"),
+            ("Y", "Copy description to clipboard"),
+            ("O", "Open the link in the description"),
+            ("Space / Enter", "Toggle task completion"),
+            ("Ctrl+Space", "Mark all shown tasks done (or undone)"),
+            ("w / W", "Next / previous status (todo, doing, done)"),
+            ("d", "Delete selected task"),
+            ("C", "Clear all completed tasks"),
+            ("+ / -", "Raise / lower priority"),
+            ("D", "Set due date"),
+            ("> / Alt+>", "Snooze due date a day / a week"),
+            ("R", "Cycle recurrence (none, daily, weekly)"),
+            ("c", "Cycle color swatch"),
+            ("p", "Pin / unpin task to the top"),
+            ("n", "Edit notes"),
+            ("E", "Edit notes in $EDITOR"),
+            ("J / Ctrl+↓", "Move task down (Manual sort)"),
+            ("K / Ctrl+↑", "Move task up (Manual sort)"),
+            ("Ctrl+k / Ctrl+j", "Move task to top / bottom (Manual sort)"),
+            ("A", "Archive task (restore in archive view)"),
+            ("u", "Undo last change"),
+            ("Ctrl+r", "Redo last undone change"),
+            (
+                "v",
+                "Visual mode: Space marks, Enter toggles, d deletes marked",
+            ),
+        ],
+    ),
+    (
+        "View",
+        &[
+            ("s", "Cycle sort mode"),
+            ("t", "Cycle theme (dark, light, high-contrast)"),
+            ("i", "Toggle detail pane"),
+            ("z", "Focus on the selected task"),
+            ("/", "Search tasks"),
+            ("T", "Cycle tag filter"),
+            ("f", "Hide / show completed tasks"),
+            ("Z", "Collapse / expand completed tasks"),
+            ("L", "Show / hide line numbers"),
+            ("Ctrl+t", "Today: overdue and due today"),
+            ("Alt+1/2/3", "Show only high / normal / low priority"),
+            ("Ctrl+a", "Toggle archive view"),
+            ("x", "Export tasks to tasks.json"),
+            ("I", "Import tasks from tasks.json"),
+            ("M", "Export tasks to tasks.md"),
+            ("X", "Export tasks to tasks.csv"),
+            ("Esc", "Clear search filter"),
+            ("S", "Show completion by tag"),
+            ("H", "Show the activity log"),
+            ("l", "Switch task list"),
+            (
+                ":",
+                "Command line (:w, :q, :sort, :filter, :delete, :clear, :list, :block, :unblock, :compact)",
+            ),
+            ("?", "Toggle this help"),
+            ("q / Ctrl+c", "Quit"),
+        ],
+    ),
+    (
+        "Input",
+        &[
+            ("Enter", "Save"),
+            ("Esc", "Cancel (asks before discarding text)"),
+            ("Ctrl+c", "Quit (asks before discarding text)"),
+            ("Backspace", "Delete character"),
+            ("Delete", "Delete character under cursor"),
+            ("← / →", "Move cursor"),
+            ("Home / End", "Jump to start / end"),
+            ("↑ / ↓, Tab", "Pick / complete a suggested #tag"),
+            ("Ctrl+w", "Delete previous word"),
+            ("Ctrl+u", "Clear input"),
+            ("Shift+Enter / Alt+Enter", "New line (notes)"),
+        ],
+    ),
+];
+
+impl Widget for &mut App {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            // Nothing is drawn where a click could land on a stale list position.
+            self.list_area = Rect::default();
+            Paragraph::new("Terminal too small")
+                .fg(self.theme.error)
+                .centered()
+                .wrap(Wrap { trim: true })
+                .render(area, buf);
+            return;
+        }
+
+        let [main_area, progress_area, footer_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(3),
+        ])
+        .areas(area);
+
+        if self.mode == Mode::Focus {
+            self.render_focus(main_area, buf);
+            self.render_progress(progress_area, buf);
+            self.render_footer(footer_area, buf);
+            return;
+        }
+
+        let list_area = if self.show_detail || self.mode == Mode::Notes {
+            let [list_area, detail_area] =
+                Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(main_area);
+            self.render_detail(detail_area, buf);
+            list_area
+        } else {
+            main_area
+        };
+
+        self.render_list(list_area, buf);
+        self.render_progress(progress_area, buf);
+        self.render_footer(footer_area, buf);
+        self.render_tag_suggestions(footer_area, buf);
+
+        match self.mode {
+            Mode::ConfirmDelete | Mode::ConfirmClearCompleted => {
+                self.render_confirm_delete(main_area, buf)
+            }
+            Mode::ConfirmDiscard { quit, .. } => self.render_confirm_discard(quit, main_area, buf),
+            Mode::ConfirmRestore => self.render_confirm_restore(main_area, buf),
+            Mode::Help => render_help(&self.theme, area, buf),
+            Mode::Stats => self.render_stats(main_area, buf),
+            Mode::Activity => self.render_activity(main_area, buf),
+            Mode::Lists => self.render_lists(main_area, buf),
+            _ => {}
+        }
+        if !self.reminders.is_empty() {
+            self.render_reminders(main_area, buf);
+        }
+    }
+}
+
+fn render_help(theme: &Theme, area: Rect, buf: &mut Buffer) {
+    let key_width = KEYBINDINGS
+        .iter()
+        .flat_map(|(_, bindings)| bindings.iter())
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (category, bindings) in KEYBINDINGS {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(*category).fg(theme.border).bold());
+        for (keys, action) in *bindings {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:<width$}  ", keys, width = key_width),
+                    Style::default().fg(theme.highlight),
+                ),
+                Span::raw(*action),
+            ]));
+        }
+    }
+
+    let help = Paragraph::new(lines).block(
+        Block::default()
+            .title(" Help (?, Esc or q to close) ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border)),
+    );
+
+    Clear.render(area, buf);
+    help.render(area, buf);
+}
+
+/// Splits a multi-line buffer into lines, drawing the cursor as a reversed cell.
+fn cursor_lines(buffer: &TextInput) -> Vec<Line<'_>> {
+    let (before, after) = buffer.split_at_cursor();
+    let mut lines: Vec<Line> = before
+        .split('\n')
+        .map(|line| Line::from(line.to_string()))
+        .collect();
+
+    let (under_cursor, rest) = match after.chars().next() {
+        Some(c) if c != '\n' => after.split_at(c.len_utf8()),
+        _ => (" ", after),
+    };
+    let mut rest_lines = rest.split('\n');
+    if let Some(current) = lines.last_mut() {
+        current.push_span(Span::styled(under_cursor, Style::default().reversed()));
+        current.push_span(Span::raw(rest_lines.next().unwrap_or_default()));
+    }
+    lines.extend(rest_lines.map(Line::from));
+    lines
+}
+
+/// Returns a rectangle of the given size centered within `area`, clamped to fit.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width.min(area.width))])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height.min(area.height))])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    area
+}
+
+impl App {
+    fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let mut title = format!(" Task Manager · {}", self.list_name());
+        if self.show_archived {
+            title.push_str(": Archive");
+        }
+        title.push(' ');
+        if !self.search_buffer.is_empty() {
+            title.push_str(&format!("(/{}) ", self.search_buffer.as_str()));
+        }
+        if let Some(tag) = &self.tag_filter {
+            title.push_str(&format!("[{}] ", tag));
+        }
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border));
+
+        let now = Utc::now();
+        let today = Local::now().date_naive();
+        let query = self.search_buffer.as_str().to_lowercase();
+        let match_style = Style::default().bold().reversed();
+        let visible = self.visible_indices();
+        let number_width = self.line_number_width();
+        let window_start = self.window.map_or(0, |window| window.start);
+        let subtasks = self.subtask_rows(&visible);
+        let progress = self.subtask_progress();
+        let rows = self.list_rows();
+        self.list_area = block.inner(area);
+
+        // Headings take up lines but can't be selected, so the list widget gets its own state
+        // with the selection shifted past the headings above it. Only the lines that fit are
+        // turned into list items, so long lists don't rebuild every row each frame.
+        let selected = self.selected_line(&rows);
+        let height = self.list_area.height as usize;
+        let start = if self.paged {
+            // Only the selection's page is shown, so the list never scrolls.
+            self.current_page(&rows) * self.page_size()
+        } else {
+            scroll_offset(self.list_offset, selected, rows.len(), height)
+        };
+        self.list_offset = start;
+        let items: Vec<ListItem> = rows
+            .iter()
+            .skip(start)
+            .take(height)
+            .map(|row| {
+                let index = match row {
+                    ListRow::Header(category) => {
+                        let name = category.as_deref().unwrap_or("No project");
+                        return ListItem::new(Line::from(format!("── {} ", name)))
+                            .style(Style::default().fg(theme.tag).bold());
+                    }
+                    ListRow::Completed(count) => {
+                        return ListItem::new(Line::from(format!("▸ {} completed", count)))
+                            .style(Style::default().fg(theme.done));
+                    }
+                    ListRow::Task(row) => visible[*row],
+                };
+                let item = &self.items[index];
+                let checkbox = item.status.checkbox();
+                let style = if item.is_done {
+                    Style::default().fg(theme.done).crossed_out()
+                } else if item.blocked {
+                    Style::default().fg(theme.muted).dim()
+                } else if item.is_overdue(today) {
+                    Style::default().fg(theme.overdue)
+                } else if item.status == TaskStatus::Doing {
+                    Style::default().fg(theme.doing)
+                } else if item.is_stale(now, self.stale_after_days) {
+                    Style::default().fg(theme.stale)
+                } else if item.priority == Priority::Low {
+                    Style::default().fg(theme.priority.low).dim()
+                } else {
+                    Style::default().fg(theme.priority.normal)
+                };
+                let marker = match item.priority {
+                    Priority::High => {
+                        Span::styled("! ", Style::default().fg(theme.priority.high).bold())
+                    }
+                    _ => Span::raw("  "),
+                };
+                let mut spans = Vec::new();
+                if let (ListRow::Task(row), 1..) = (row, number_width) {
+                    spans.push(Span::styled(
+                        format!(
+                            "{:>width$} ",
+                            window_start + row + 1,
+                            width = number_width - 1
+                        ),
+                        Style::default().fg(theme.muted).not_crossed_out(),
+                    ));
+                }
+                if self.mode == Mode::Visual || !self.marked.is_empty() {
+                    spans.push(if self.marked.contains(&item.id) {
+                        Span::styled("● ", Style::default().fg(theme.border))
+                    } else {
+                        Span::raw("○ ")
+                    });
+                }
+                if let ListRow::Task(row) = row
+                    && subtasks[*row]
+                {
+                    spans.push(Span::raw("  "));
+                }
+                spans.extend([Span::raw(format!("{} ", checkbox)), marker]);
+                if item.pinned {
+                    spans.push(Span::raw("📌 "));
+                }
+                if item.blocked && !item.is_done {
+                    spans.push(Span::raw("🔒 "));
+                }
+                if let Some(color) = swatch_color(item.color) {
+                    spans.push(Span::styled(
+                        "■ ",
+                        Style::default().fg(color).not_crossed_out(),
+                    ));
+                }
+                let field = search_task(item, &query).map(|(field, _)| field);
+                spans.extend(highlight_matches(&item.description, &query, match_style));
+                if let Some((done, total)) = progress.get(&item.id) {
+                    spans.push(Span::styled(
+                        format!(" {}/{}", done, total),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+                if item.recurrence != Recurrence::None {
+                    spans.push(Span::raw(format!(" ↻ {}", item.recurrence.label())));
+                }
+                for tag in &item.tags {
+                    spans.push(Span::styled(" ", Style::default().fg(theme.tag)));
+                    if field == Some(SearchField::Tag) {
+                        spans.extend(
+                            highlight_matches(tag, &query, match_style)
+                                .into_iter()
+                                .map(|span| span.fg(theme.tag)),
+                        );
+                    } else {
+                        spans.push(Span::styled(tag.as_str(), Style::default().fg(theme.tag)));
+                    }
+                }
+                // Notes aren't shown in the list, so say why the task matched.
+                if field == Some(SearchField::Notes) {
+                    spans.push(Span::styled(
+                        "  [in notes]",
+                        Style::default().fg(theme.muted).not_crossed_out(),
+                    ));
+                }
+                if let (Some(due), Some(label)) = (item.due_date, item.due_label()) {
+                    if item.is_done {
+                        spans.push(Span::raw(format!("  (due {})", label)));
+                    } else {
+                        let color = match due.cmp(&today) {
+                            Ordering::Less => Some(theme.overdue),
+                            Ordering::Equal => Some(theme.due_today),
+                            Ordering::Greater => None,
+                        };
+                        let mut style = Style::default();
+                        if let Some(color) = color {
+                            style = style.fg(color);
+                        }
+                        let at = item
+                            .due_time
+                            .map(|time| format!(" at {}", time.format(DUE_TIME_FORMAT)))
+                            .unwrap_or_default();
+                        spans.push(Span::styled(
+                            format!("  ({}{})", relative_due(due, today), at),
+                            style,
+                        ));
+                    }
+                }
+                let mut timestamps = Vec::new();
+                if let Some(created_at) = item.created_at {
+                    timestamps.push(format!("added {}", local_date(created_at)));
+                }
+                if let Some(completed_at) = item.completed_at.filter(|_| item.is_done) {
+                    timestamps.push(format!("done {}", local_date(completed_at)));
+                }
+                if !timestamps.is_empty() {
+                    spans.push(Span::styled(
+                        format!("  · {}", timestamps.join(", ")),
+                        Style::default().fg(theme.muted).not_crossed_out(),
+                    ));
+                }
+                let line = Line::from(spans);
+                ListItem::new(line).style(style)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_symbol(HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().fg(theme.highlight).bold());
+        let mut state =
+            ListState::default().with_selected(selected.and_then(|line| line.checked_sub(start)));
+        StatefulWidget::render(list, area, buf, &mut state);
+
+        // The scrollbar spans the whole list, tasks outside a loaded window included, and is
+        // left out when everything fits.
+        let (length, position) = match self.window {
+            Some(window) => (
+                window.counts.total,
+                self.selected_position().unwrap_or(window.start),
+            ),
+            None => (rows.len(), selected.unwrap_or(start)),
+        };
+        if length > height {
+            let mut scrollbar = ScrollbarState::new(length)
+                .position(position)
+                .viewport_content_length(height);
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .style(Style::default().fg(theme.border))
+                .render(area.inner(Margin::new(0, 1)), buf, &mut scrollbar);
+        }
+    }
+
+    /// Shows the selected task's description and notes centered on their own, for focus mode.
+    fn render_focus(&self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let visible = self.visible_indices();
+        let position = self.state.selected().map_or(0, |row| row + 1);
+        let block = Block::default()
+            .title(format!(" Focus {}/{} ", position, visible.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let Some(item) = self.selected_item() else {
+            return;
+        };
+        let description = Line::from(item.description.as_str())
+            .bold()
+            .style(if item.is_done {
+                Style::default().fg(theme.done).crossed_out()
+            } else {
+                Style::default()
+            });
+        let mut lines = vec![
+            Line::from(format!(
+                "{} {}",
+                item.status.checkbox(),
+                item.status.label()
+            ))
+            .fg(theme.muted),
+            Line::from(""),
+            description,
+            Line::from(""),
+        ];
+        if item.notes.is_empty() {
+            lines.push(Line::from("No notes").fg(theme.muted));
+        } else {
+            lines.extend(item.notes.lines().map(Line::from));
+        }
+
+        // Size the text area by its wrapped height so it sits in the middle of the screen.
+        let [column] = Layout::horizontal([Constraint::Max(72)])
+            .flex(layout::Flex::Center)
+            .areas(inner);
+        let width = column.width.max(1) as usize;
+        let height: usize = lines
+            .iter()
+            .map(|line| line.width().div_ceil(width).max(1))
+            .sum();
+        let [text_area] = Layout::vertical([Constraint::Length(height as u16)])
+            .flex(layout::Flex::Center)
+            .areas(column);
+        Paragraph::new(lines)
+            .centered()
+            .wrap(Wrap { trim: false })
+            .render(text_area, buf);
+    }
+
+    fn render_detail(&self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let block = Block::default()
+            .title(" Details ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(if self.mode == Mode::Notes {
+                theme.highlight
+            } else {
+                theme.border
+            }));
+
+        let Some(item) = self.selected_item() else {
+            Paragraph::new("No task selected")
+                .style(Style::default().fg(theme.muted))
+                .block(block)
+                .render(area, buf);
+            return;
+        };
+
+        let field = |label: &str, value: String| {
+            Line::from(vec![
+                Span::styled(format!("{:<10}", label), Style::default().fg(theme.muted)),
+                Span::raw(value),
+            ])
+        };
+        let mut lines = vec![
+            Line::from(item.description.as_str()).bold(),
+            Line::from(""),
+            field("Status", item.status.label().to_string()),
+            field("Priority", item.priority.label().to_string()),
+        ];
+        if let Some(category) = &item.category {
+            lines.push(field("Project", category.clone()));
+        }
+        if let Some(parent) = item
+            .parent_id
+            .and_then(|parent_id| self.items.iter().find(|other| other.id == parent_id))
+        {
+            lines.push(field("Parent", parent.description.clone()));
+        }
+        if let Some(blocker) = item
+            .blocked_by
+            .and_then(|blocker_id| self.items.iter().find(|other| other.id == blocker_id))
+        {
+            let done = if blocker.is_done { " (done)" } else { "" };
+            lines.push(field(
+                "Waits on",
+                format!("{}{}", blocker.description, done),
+            ));
+        }
+        if let Some((done, total)) = self.subtask_progress().get(&item.id) {
+            lines.push(field("Subtasks", format!("{}/{} done", done, total)));
+        }
+        if let Some(due) = item.due_label() {
+            lines.push(field("Due", due));
+        }
+        if item.recurrence != Recurrence::None {
+            lines.push(field("Repeats", item.recurrence.label().to_string()));
+        }
+        if item.color != TaskColor::None {
+            lines.push(field("Color", item.color.label().to_string()));
+        }
+        if !item.tags.is_empty() {
+            lines.push(field("Tags", item.tags.join(" ")));
+        }
+        if let Some(created_at) = item.created_at {
+            lines.push(field("Added", local_date(created_at)));
+        }
+        if let Some(completed_at) = item.completed_at.filter(|_| item.is_done) {
+            lines.push(field("Done", local_date(completed_at)));
+        }
+        if let Some(updated_at) = item.updated_at {
+            lines.push(field("Modified", relative_time(updated_at, Utc::now())));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("Notes").fg(theme.border).bold());
+
+        if self.mode == Mode::Notes {
+            lines.extend(cursor_lines(&self.input_buffer));
+        } else if item.notes.is_empty() {
+            lines.push(Line::from("No notes, press n to add some").fg(theme.muted));
+        } else {
+            lines.extend(item.notes.lines().map(Line::from));
+        }
+
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .render(area, buf);
+    }
+
+    fn render_reminders(&self, area: Rect, buf: &mut Buffer) {
+        let now = Local::now().naive_local();
+        let lines: Vec<Line> = self
+            .reminders
+            .iter()
+            .map(|item| {
+                let style = Style::default();
+                let (when, style) = if item.is_due_within(now, REMINDER_WINDOW) {
+                    let due_at = item
+                        .due_date
+                        .zip(item.due_time)
+                        .map(|(date, time)| date.and_time(time));
+                    let minutes = due_at.map_or(0, |due_at| (due_at - now).num_minutes());
+                    (
+                        format!("due in {} min", minutes.max(1)),
+                        style.fg(self.theme.overdue).bold().reversed(),
+                    )
+                } else if item.due_date.is_some_and(|due| due < now.date()) {
+                    (
+                        format!("overdue since {}", item.due_label().unwrap_or_default()),
+                        style.fg(self.theme.overdue),
+                    )
+                } else {
+                    match item.due_time {
+                        Some(time) if time <= now.time() => (
+                            format!("overdue since {}", time.format(DUE_TIME_FORMAT)),
+                            style.fg(self.theme.overdue),
+                        ),
+                        Some(time) => (
+                            format!("due today at {}", time.format(DUE_TIME_FORMAT)),
+                            style.fg(self.theme.highlight),
+                        ),
+                        None => ("due today".to_string(), style.fg(self.theme.highlight)),
+                    }
+                };
+                Line::from(vec![
+                    Span::raw(format!(" {}  ", item.description)),
+                    Span::styled(when, style),
+                ])
+            })
+            .collect();
+
+        let dialog_area = centered_rect(70, lines.len() as u16 + 2, area);
+        Clear.render(dialog_area, buf);
+        Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(format!(
+                        " {} tasks due, press any key to dismiss ",
+                        self.reminders.len()
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.overdue)),
+            )
+            .render(dialog_area, buf);
+    }
+
+    /// The activity log, newest first, over the task list.
+    fn render_activity(&mut self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let block = Block::default()
+            .title(format!(" Activity ({} entries) ", self.activity.len()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.border));
+        Clear.render(area, buf);
+        if self.activity.is_empty() {
+            Paragraph::new("Nothing has happened yet")
+                .style(Style::default().fg(theme.muted))
+                .block(block)
+                .render(area, buf);
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .activity
+            .iter()
+            .map(|entry| {
+                let color = match entry.activity {
+                    Activity::Added => theme.info,
+                    Activity::Completed => theme.done,
+                    Activity::Reopened => theme.highlight,
+                    Activity::Deleted => theme.error,
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        entry
+                            .at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M  ")
+                            .to_string(),
+                        Style::default().fg(theme.muted),
+                    ),
+                    Span::styled(
+                        format!("{:<10}", entry.activity.label()),
+                        Style::default().fg(color),
+                    ),
+                    Span::raw(entry.description.as_str()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_symbol(HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().fg(theme.highlight).bold());
+        StatefulWidget::render(list, area, buf, &mut self.activity_state);
+    }
+
+    /// The list picker, with the list being shown marked.
+    fn render_lists(&mut self, area: Rect, buf: &mut Buffer) {
+        let theme = self.theme;
+        let active = self.list_name().to_string();
+        let items: Vec<ListItem> = self
+            .list_names
+            .iter()
+            .map(|name| {
+                let mut line = Line::from(name.as_str());
+                if *name == active {
+                    line.push_span(Span::styled("  (open)", Style::default().fg(theme.muted)));
+                }
+                ListItem::new(line)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(" Lists ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.border)),
+            )
+            .highlight_symbol(HIGHLIGHT_SYMBOL)
+            .highlight_style(Style::default().fg(theme.highlight).bold());
+
+        let dialog_area = centered_rect(40, self.list_names.len() as u16 + 2, area);
+        Clear.render(dialog_area, buf);
+        StatefulWidget::render(list, dialog_area, buf, &mut self.lists_state);
+    }
+
+    fn render_stats(&self, area: Rect, buf: &mut Buffer) {
+        let streak = format!(" {}-day streak ", self.streak);
+        let block = Block::default()
+            .title(" Completion by tag (S, Esc or q to close) ")
+            .title(Line::from(streak).fg(self.theme.info).right_aligned())
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.theme.border));
+
+        let chart_height = if self.completions_per_day.is_empty() {
+            0
+        } else {
+            CHART_HEIGHT
+        };
+        let height = self.tag_stats.len().max(1) as u16 + 2 + chart_height;
+        let dialog_area = centered_rect(70, height, area);
+        Clear.render(dialog_area, buf);
+        let [inner, chart_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(chart_height)])
+                .areas(block.inner(dialog_area));
+        block.render(dialog_area, buf);
+        self.render_completion_chart(chart_area, buf);
+
+        if self.tag_stats.is_empty() {
+            Paragraph::new("No tasks yet")
+                .fg(self.theme.muted)
+                .render(inner, buf);
+            return;
+        }
+
+        let name_width = self
+            .tag_stats
+            .iter()
+            .map(|stats| stats.name.chars().count())
+            .max()
+            .unwrap_or(0) as u16;
+        let rows = Layout::vertical(vec![Constraint::Length(1); self.tag_stats.len()]).split(inner);
+        for (stats, row) in self.tag_stats.iter().zip(rows.iter()) {
+            let [name_area, gauge_area, count_area] = Layout::horizontal([
+                Constraint::Length(name_width + 1),
+                Constraint::Min(10),
+                Constraint::Length(10),
+            ])
+            .areas(*row);
+
+            Span::styled(stats.name.as_str(), Style::default().fg(self.theme.tag))
+                .render(name_area, buf);
+            LineGauge::default()
+                .filled_style(Style::default().fg(self.theme.info))
+                .unfilled_style(Style::default().fg(self.theme.muted))
+                .ratio(stats.done as f64 / stats.total as f64)
+                .render(gauge_area, buf);
+            Line::from(format!("{}/{}", stats.done, stats.total))
+                .right_aligned()
+                .render(count_area, buf);
+        }
+    }
+
+    /// Bars of how many tasks were completed each day, labeled with the day of the month and
+    /// spread across the width of the statistics overlay.
+    fn render_completion_chart(&self, area: Rect, buf: &mut Buffer) {
+        if self.completions_per_day.is_empty() {
+            return;
+        }
+        let block = Block::default()
+            .title(format!(
+                " Completed per day, last {} days ",
+                self.completions_per_day.len()
+            ))
+            .borders(Borders::TOP)
+            .border_style(Style::default().fg(self.theme.border));
+        let days = self.completions_per_day.len() as u16;
+        let bar_width = ((block.inner(area).width + 1) / days)
+            .saturating_sub(1)
+            .max(1);
+        let bars: Vec<Bar> = self
+            .completions_per_day
+            .iter()
+            .map(|(day, count)| Bar::new(*count).label(day.format("%d").to_string()))
+            .collect();
+        BarChart::new(bars)
+            .block(block)
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(self.theme.info))
+            .label_style(Style::default().fg(self.theme.muted))
+            .render(area, buf);
+    }
+
+    fn render_confirm_delete(&self, area: Rect, buf: &mut Buffer) {
+        let question = if self.mode == Mode::ConfirmClearCompleted {
+            format!("Delete {} completed tasks?", self.counts().done)
+        } else if !self.marked.is_empty() {
+            format!("Delete {} marked tasks?", self.marked.len())
+        } else if let Some(item) = self.selected_item().filter(|_| self.delete_count > 1) {
+            format!(
+                "Delete {} tasks starting at '{}'?",
+                self.delete_count, item.description
+            )
+        } else if let Some(item) = self.selected_item() {
+            format!("Delete '{}'?", item.description)
+        } else {
+            return;
+        };
+
+        let dialog = Paragraph::new(vec![
+            Line::from(question),
+            Line::from(""),
+            Line::from("(y/n)").fg(self.theme.muted),
+        ])
+        .centered()
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(" Confirm Delete ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(self.theme.error)),
+        );
+
+        let dialog_area = centered_rect(50, 7, area);
+        Clear.render(dialog_area, buf);
+        dialog.render(dialog_area, buf);
+    }
+
+    fn render_confirm_discard(&self, quit: bool, area: Rect, buf: &mut Buffer) {
+        let question = if quit {
+            "Discard unsaved input and quit?"
+        } else {
+            "Discard unsaved input?"
+        };
+        let dialog = Paragraph::new(vec![
+            Line::from(question),
+            Line::from(""),
+            Line::from("(y/n)").fg(self.theme.muted),
+        ])
+        .centered()
+        .block(
+            Block::default()
+                .title(" Unsaved Input ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(self.theme.error)),
+        );
+
+        let dialog_area = centered_rect(50, 5, area);
+        Clear.render(dialog_area, buf);
+        dialog.render(dialog_area, buf);
+    }
+
+    fn render_confirm_restore(&self, area: Rect, buf: &mut Buffer) {
+        let dialog = Paragraph::new(vec![
+            Line::from("Restore the task you were typing when the app last closed?"),
+            Line::from(""),
+            Line::from(format!("\"{}\"", self.draft.trim())).fg(self.theme.highlight),
+            Line::from(""),
+            Line::from("(y/n)").fg(self.theme.muted),
+        ])
+        .centered()
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(" Unsaved Draft ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(self.theme.info)),
+        );
+
+        let dialog_area = centered_rect(60, 9, area);
+        Clear.render(dialog_area, buf);
+        dialog.render(dialog_area, buf);
+    }
+
+    fn render_progress(&self, area: Rect, buf: &mut Buffer) {
+        let counts = self.counts();
+        let ratio = if counts.total == 0 {
+            0.0
+        } else {
+            counts.done as f64 / counts.total as f64
+        };
+
+        Gauge::default()
+            .gauge_style(Style::default().fg(self.theme.info).bg(self.theme.muted))
+            .ratio(ratio)
+            .label(format!("{:.0}%", ratio * 100.0))
+            .render(area, buf);
+    }
+
+    fn render_footer(&mut self, area: Rect, buf: &mut Buffer) {
+        let text = match self.mode {
+            Mode::Normal => format!(
+                "{}{} a: Add | e: Edit | Space: Toggle | d: Delete | s: Sort ({}) | ?: Help | q: Quit ",
+                self.pending_keys()
+                    .map_or(String::new(), |keys| format!(" Pending: {} |", keys)),
+                match self.active_filters() {
+                    filters if filters.is_empty() => String::new(),
+                    filters => format!(" [{}] |", filters.join(", ")),
+                },
+                self.sort_mode.label()
+            ),
+            Mode::Input if self.subtask_parent.is_some() => {
+                " Type subtask description, Enter to save, Esc to cancel ".to_string()
+            }
+            Mode::Input => " Type task description, Enter to save, Esc to cancel ".to_string(),
+            Mode::Edit => " Edit task description, Enter to save, Esc to cancel ".to_string(),
+            Mode::DueDate => {
+                " Enter due date as YYYY-MM-DD, today, tomorrow, fri, next week or in 3 days, then an optional HH:MM; empty to clear ".to_string()
+            }
+            Mode::Notes => {
+                " Editing notes: Shift+Enter/Alt+Enter: New line | Enter: Save | Esc: Cancel "
+                    .to_string()
+            }
+            Mode::Search => " Type to filter, Enter to keep filter, Esc to clear ".to_string(),
+            Mode::ConfirmDelete | Mode::ConfirmClearCompleted => {
+                " y: Delete | n/Esc: Cancel ".to_string()
+            }
+            Mode::ConfirmDiscard { .. } => " y: Discard | n/Esc: Keep typing ".to_string(),
+            Mode::ConfirmRestore => " y: Restore into a new task | n/Esc: Discard ".to_string(),
+            Mode::Help => " ?/Esc/q: Close help ".to_string(),
+            Mode::Stats => " S/Esc/q: Close statistics ".to_string(),
+            Mode::Lists => {
+                " j/k: Move | Enter: Open list | :list <name>: New list | l/Esc/q: Close "
+                    .to_string()
+            }
+            Mode::Activity => " j/k: Scroll | g/G: Newest / oldest | H/Esc/q: Close ".to_string(),
+            Mode::Focus => {
+                " FOCUS | j/k: Next / previous task | Space: Toggle | Esc: Back to list "
+                    .to_string()
+            }
+            Mode::Command => {
+                " :w :q :wq :sort <mode> :filter done|none :delete <n> :clear :list <name> :block <n> :unblock :compact | Esc: Cancel "
+                    .to_string()
+            }
+            Mode::Visual => format!(
+                " VISUAL ({} marked) | Space: Mark | Enter: Toggle marked | d: Delete marked | v/Esc: Exit ",
+                self.marked.len()
+            ),
+            Mode::ConfirmImport => {
+                " Import tasks.json: m: Merge (skip duplicates) | r: Replace all | Esc: Cancel "
+                    .to_string()
+            }
+        };
+
+        let (content, color, mut title) = match self.input_prompt() {
+            Some((prompt, title, buffer)) => {
+                let (before, after) = buffer.split_at_cursor();
+                let mut chars = after.chars();
+                let under_cursor = chars.next().map_or(" ".to_string(), String::from);
+                let content = Line::from(vec![
+                    Span::raw(format!(" {}{}", prompt, before)),
+                    Span::styled(under_cursor, Style::default().reversed()),
+                    Span::raw(chars.as_str()),
+                ]);
+                (content, self.theme.highlight, Line::from(title))
+            }
+            None => (Line::from(text), self.theme.muted, Line::default()),
+        };
+        let mut border_color = color;
+
+        if let Some(status) = &self.status {
+            let (message, color) = match status {
+                Status::Info(message) => (message, self.theme.info),
+                Status::Error(message) => (message, self.theme.error),
+            };
+            border_color = color;
+            title = Line::from(format!(" {} ", message)).fg(color).bold();
+        }
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(border_color))
+            .title(title);
+        // Changes to the demo database are never saved, so it shows a marker instead.
+        if self.demo {
+            block = block.title(
+                Line::from(" [DEMO] ")
+                    .fg(self.theme.error)
+                    .bold()
+                    .right_aligned(),
+            );
+        } else if self
+            .saved_at
+            .is_some_and(|saved_at| saved_at.elapsed() < SAVED_INDICATOR)
+        {
+            block = block.title(Line::from(" saved ✓ ").fg(self.theme.info).right_aligned());
+        }
+        if self.paged {
+            let rows = self.list_rows();
+            block = block.title(
+                Line::from(format!(
+                    " Page {}/{} ",
+                    self.current_page(&rows) + 1,
+                    self.page_count(&rows)
+                ))
+                .right_aligned(),
+            );
+        }
+        let mut block = block.title(Line::from(self.progress_summary()).right_aligned());
+        // Only loaded tasks count, since a windowed list stays quick however long it gets.
+        if self.large_list_warning > 0 && self.items.len() > self.large_list_warning {
+            block = block.title_bottom(
+                Line::from(format!(
+                    " {} tasks loaded, archive or filter to keep things quick ",
+                    self.items.len()
+                ))
+                .fg(self.theme.muted),
+            );
+        }
+        let block = block.title_bottom(
+            Line::from(format!(" {} ", Local::now().format("%H:%M")))
+                .fg(self.theme.muted)
+                .right_aligned(),
+        );
+
+        Paragraph::new(content)
+            .style(Style::default().fg(color))
+            .block(block)
+            .render(area, buf);
+    }
+
+    /// The prompt, title and buffer of the footer's text input, when one is open.
+    fn input_prompt(&self) -> Option<(&'static str, &'static str, &TextInput)> {
+        match self.mode {
+            Mode::Input if self.subtask_parent.is_some() => {
+                Some(("New subtask: ", " Input Mode ", &self.input_buffer))
+            }
+            Mode::Input => Some(("New task: ", " Input Mode ", &self.input_buffer)),
+            Mode::Edit => Some(("Edit task: ", " Edit Mode ", &self.input_buffer)),
+            Mode::DueDate => Some(("Due date: ", " Due Date ", &self.input_buffer)),
+            Mode::Search => Some(("Search: ", " Search ", &self.search_buffer)),
+            Mode::Command => Some((":", " Command ", &self.input_buffer)),
+            _ => None,
+        }
+    }
+
+    /// Lists the tags completing the one being typed just above the footer, lined up with
+    /// where the tag starts in the prompt.
+    fn render_tag_suggestions(&self, footer_area: Rect, buf: &mut Buffer) {
+        let (Some(partial), Some((prompt, _, buffer))) = (self.partial_tag(), self.input_prompt())
+        else {
+            return;
+        };
+        if self.tag_suggestions.is_empty() {
+            return;
+        }
+        let (before, _) = buffer.split_at_cursor();
+        let typed =
+            Line::from(format!(" {}{}", prompt, before)).width() - Line::from(partial).width();
+        let width = self
+            .tag_suggestions
+            .iter()
+            .map(|tag| Line::from(tag.as_str()).width())
+            .max()
+            .unwrap_or(0) as u16
+            + 4;
+        let width = width.min(footer_area.width);
+        let height = (self.tag_suggestions.len() as u16 + 2).min(footer_area.y);
+        // Back by the border and padding so each tag starts under the one being typed.
+        let x = (footer_area.x + 1 + typed as u16)
+            .saturating_sub(2)
+            .min(footer_area.right() - width);
+        let area = Rect::new(x, footer_area.y - height, width, height);
+
+        let items = self
+            .tag_suggestions
+            .iter()
+            .map(|tag| ListItem::new(format!(" {} ", tag)));
+        let list = List::new(items)
+            .highlight_style(Style::default().fg(self.theme.highlight).reversed())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(self.theme.border)),
+            );
+        let mut state = ListState::default().with_selected(Some(self.suggestion));
+        Clear.render(area, buf);
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+
+    /// The count and `g` typed so far for a command that hasn't completed yet.
+    fn pending_keys(&self) -> Option<String> {
+        let mut keys = self.pending_count.map(|count| count.to_string());
+        if self.pending_g {
+            keys.get_or_insert_default().push('g');
+        }
+        keys
+    }
+
+    /// Labels for every filter narrowing the list, shown together in the footer.
+    fn active_filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if self.today_view.is_some() {
+            filters.push("Today".to_string());
+        }
+        if let Some(priority) = self.priority_filter {
+            filters.push(format!("{} priority", priority.label()));
+        }
+        if self.hide_done {
+            filters.push("Hiding done".to_string());
+        } else if self.completed_collapsed {
+            filters.push("Done collapsed".to_string());
+        }
+        if let Some(tag) = &self.tag_filter {
+            filters.push(format!("Tag {}", tag));
+        }
+        if !self.search_buffer.is_empty() {
+            filters.push(format!("/{}", self.search_buffer.as_str()));
+        }
+        filters
+    }
+
+    fn progress_summary(&self) -> String {
+        let counts = self.counts();
+        if counts.total == 0 {
+            return " No tasks ".to_string();
+        }
+        let stale = match counts.stale {
+            0 => String::new(),
+            stale => format!(", {} stale", stale),
+        };
+        format!(
+            " {}/{} done ({} remaining{}) ",
+            counts.done,
+            counts.total,
+            counts.total - counts.done,
+            stale
+        )
+    }
+}
+
+/// Describes `due` relative to `today`, e.g. "due tomorrow" or "3 days overdue".
+fn relative_due(due: NaiveDate, today: NaiveDate) -> String {
+    match (due - today).num_days() {
+        0 => "due today".to_string(),
+        1 => "due tomorrow".to_string(),
+        -1 => "1 day overdue".to_string(),
+        days if days > 1 => format!("due in {} days", days),
+        days => format!("{} days overdue", -days),
+    }
+}
+
+/// Describes how long before `now` `then` was, e.g. "just now" or "3 hours ago".
+fn relative_time(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let elapsed = now - then;
+    let (count, unit) = if elapsed.num_minutes() < 1 {
+        return "just now".to_string();
+    } else if elapsed.num_hours() < 1 {
+        (elapsed.num_minutes(), "minute")
+    } else if elapsed.num_days() < 1 {
+        (elapsed.num_hours(), "hour")
+    } else {
+        (elapsed.num_days(), "day")
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// The terminal color drawn for a task's swatch, or `None` when it has no color.
+fn swatch_color(color: TaskColor) -> Option<Color> {
+    match color {
+        TaskColor::None => None,
+        TaskColor::Red => Some(Color::Red),
+        TaskColor::Yellow => Some(Color::Yellow),
+        TaskColor::Green => Some(Color::Green),
+        TaskColor::Cyan => Some(Color::Cyan),
+        TaskColor::Blue => Some(Color::Blue),
+        TaskColor::Magenta => Some(Color::Magenta),
+    }
+}
+
+/// The first of `len` lines to show in a list `height` lines tall, scrolled as little as
+/// possible from `offset` to keep the `selected` line and the lines around it in view.
+fn scroll_offset(offset: usize, selected: Option<usize>, len: usize, height: usize) -> usize {
+    let padding = SCROLL_PADDING.min(height.saturating_sub(1) / 2);
+    let mut offset = offset;
+    if let Some(selected) = selected {
+        if selected < offset + padding {
+            offset = selected.saturating_sub(padding);
+        } else if selected + padding >= offset + height {
+            offset = selected + padding + 1 - height;
+        }
+    }
+    offset.min(len.saturating_sub(height))
+}
+
+/// Splits `text` into spans with the characters fuzzy-matched by `query` styled with `style`.
+fn highlight_matches<'a>(text: &'a str, query: &str, style: Style) -> Vec<Span<'a>> {
+    let positions = fuzzy_match(text, query).map_or(Vec::new(), |found| found.positions);
+    let char_end = |pos: usize| pos + text[pos..].chars().next().map_or(0, char::len_utf8);
+
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut positions = positions.into_iter().peekable();
+    while let Some(start) = positions.next() {
+        // Adjacent matched characters share one span.
+        let mut end = char_end(start);
+        while let Some(next) = positions.next_if_eq(&end) {
+            end = char_end(next);
+        }
+        if plain_start < start {
+            spans.push(Span::raw(&text[plain_start..start]));
+        }
+        spans.push(Span::styled(&text[start..end], style));
+        plain_start = end;
+    }
+    if plain_start < text.len() || spans.is_empty() {
+        spans.push(Span::raw(&text[plain_start..]));
+    }
+    spans
+}
+
+fn local_date(timestamp: DateTime<Utc>) -> String {
+    timestamp
+        .with_timezone(&Local)
+        .format(DATE_FORMAT)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn highlight_matches_styles_fuzzy_matched_runs() {
+        let style = Style::default().bold();
+        let spans = highlight_matches("task manager", "tman", style);
+        let text: Vec<(&str, bool)> = spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style == style))
+            .collect();
+        assert_eq!(
+            text,
+            [("t", true), ("ask ", false), ("man", true), ("ager", false)]
+        );
+    }
+
+    #[test]
+    fn scroll_offset_moves_only_as_far_as_the_selection_needs() {
+        // 100 lines in a 10 line window, one line of padding around the selection.
+        assert_eq!(scroll_offset(0, Some(5), 100, 10), 0);
+        assert_eq!(scroll_offset(0, Some(9), 100, 10), 1);
+        assert_eq!(scroll_offset(40, Some(40), 100, 10), 39);
+        assert_eq!(scroll_offset(40, Some(45), 100, 10), 40);
+        assert_eq!(scroll_offset(0, Some(99), 100, 10), 90);
+        // A list that shrank doesn't leave the window scrolled past its end.
+        assert_eq!(scroll_offset(90, None, 20, 10), 10);
+        assert_eq!(scroll_offset(5, Some(2), 3, 10), 0);
+    }
+
+    #[test]
+    fn relative_time_picks_the_largest_whole_unit() {
+        let now = DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let ago = |seconds| relative_time(now - chrono::TimeDelta::seconds(seconds), now);
+        assert_eq!(ago(30), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3 * 3600 + 59), "3 hours ago");
+        assert_eq!(ago(2 * 86400), "2 days ago");
+    }
+
+    #[test]
+    fn relative_due_describes_days_around_today() {
+        let today = date("2024-03-01");
+        assert_eq!(relative_due(date("2024-03-01"), today), "due today");
+        assert_eq!(relative_due(date("2024-03-02"), today), "due tomorrow");
+        assert_eq!(relative_due(date("2024-02-29"), today), "1 day overdue");
+        assert_eq!(relative_due(date("2024-03-04"), today), "due in 3 days");
+        assert_eq!(relative_due(date("2024-02-27"), today), "3 days overdue");
+    }
+}
@@ -0,0 +1,80 @@
+use std::process::{Command, Stdio};
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+
+/// Characters that end a sentence around a link rather than belonging to it.
+const TRAILING: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\''];
+
+/// The first `http://` or `https://` link in `text`, without punctuation that follows it.
+pub fn find_url(text: &str) -> Option<&str> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            let start = word.find("http://").or_else(|| word.find("https://"))?;
+            Some(word[start..].trim_end_matches(TRAILING))
+        })
+        .find(|url| !url.ends_with("//"))
+}
+
+/// Opens `url` in the default browser with the platform's opener, waiting only for the
+/// opener itself to hand the link over.
+pub fn open(url: &str) -> Result<()> {
+    let status = opener(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .wrap_err("Failed to launch a browser")?;
+    if !status.success() {
+        bail!("The browser opener exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("open");
+    command.arg(url);
+    command
+}
+
+/// Hands the link straight to the URL protocol handler. Going through `cmd /C start` would
+/// let cmd.exe treat a `&` in a query string as the start of another command.
+#[cfg(windows)]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("rundll32");
+    command.args(["url.dll,FileProtocolHandler", url]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn opener(url: &str) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(url);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_first_link_without_trailing_punctuation() {
+        assert_eq!(
+            find_url("read (https://ratatui.rs/recipes). then http://example.com"),
+            Some("https://ratatui.rs/recipes")
+        );
+        assert_eq!(
+            find_url("see <http://example.com/a?b=1>"),
+            Some("http://example.com/a?b=1")
+        );
+    }
+
+    #[test]
+    fn ignores_text_without_a_link() {
+        assert_eq!(find_url("call the bank"), None);
+        assert_eq!(find_url("type https:// then the host"), None);
+    }
+}
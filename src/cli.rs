@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::{Result, eyre::bail};
+
+const DEFAULT_DB_PATH: &str = "tasks.db";
+const DB_ENV_VAR: &str = "TASK_MANAGER_DB";
+
+const USAGE: &str =
+    "Usage: task-manager-tui [--db <path>] [--import-file <path>] [--demo] [<command>]
+
+Commands:
+  add <description>     Add a task and exit without starting the UI
+  list                  Print the tasks that aren't archived and exit
+
+Options:
+  --db <path>           Path to the SQLite database (default: $TASK_MANAGER_DB or tasks.db)
+  --import-file <path>  Add tasks from a text file, one per line, before starting
+  --demo                Try the app on sample tasks in memory; nothing is saved
+  -h, --help            Print this help message";
+
+/// A subcommand run instead of the interactive app.
+pub enum CliCommand {
+    Add(String),
+    List,
+}
+
+pub struct Args {
+    db: Option<PathBuf>,
+    /// A plain-text task list to import before the UI starts.
+    pub import_file: Option<PathBuf>,
+    pub command: Option<CliCommand>,
+    /// Run on seeded sample tasks in an in-memory database instead of the real one.
+    pub demo: bool,
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self {
+            db: None,
+            import_file: None,
+            command: None,
+            demo: false,
+            help: false,
+        };
+        let mut positional = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--db" => match args.next() {
+                    Some(path) => parsed.db = Some(PathBuf::from(path)),
+                    None => bail!("--db requires a path\n\n{}", USAGE),
+                },
+                "--import-file" => match args.next() {
+                    Some(path) => parsed.import_file = Some(expand_home(Path::new(&path))),
+                    None => bail!("--import-file requires a path\n\n{}", USAGE),
+                },
+                "--demo" => parsed.demo = true,
+                "-h" | "--help" => parsed.help = true,
+                other => {
+                    if let Some(path) = other.strip_prefix("--db=") {
+                        parsed.db = Some(PathBuf::from(path));
+                    } else if let Some(path) = other.strip_prefix("--import-file=") {
+                        parsed.import_file = Some(expand_home(Path::new(path)));
+                    } else if other.starts_with('-') && positional.is_empty() {
+                        bail!("unknown argument '{}'\n\n{}", other, USAGE);
+                    } else {
+                        positional.push(other.to_string());
+                    }
+                }
+            }
+        }
+
+        parsed.command = match positional.split_first() {
+            None => None,
+            Some((command, rest)) if command == "add" && !rest.is_empty() => {
+                Some(CliCommand::Add(rest.join(" ")))
+            }
+            Some((command, _)) if command == "add" => {
+                bail!("add requires a task description\n\n{}", USAGE)
+            }
+            Some((command, [])) if command == "list" => Some(CliCommand::List),
+            Some((command, _)) => bail!("unknown command '{}'\n\n{}", command, USAGE),
+        };
+        if parsed.demo && parsed.command.is_some() {
+            bail!("--demo only applies to the interactive app\n\n{}", USAGE);
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn usage() -> &'static str {
+        USAGE
+    }
+
+    /// Resolves the database path from `--db`, then `$TASK_MANAGER_DB`, then the default,
+    /// expanding a leading `~` to the home directory.
+    pub fn db_path(&self) -> PathBuf {
+        let path = self
+            .db
+            .clone()
+            .or_else(|| std::env::var_os(DB_ENV_VAR).map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DB_PATH));
+
+        expand_home(&path)
+    }
+
+    /// The directory holding the database, where the config files and exports live too.
+    pub fn data_dir(&self) -> PathBuf {
+        self.db_path()
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf()
+    }
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    let Ok(rest) = path.strip_prefix("~") else {
+        return path.to_path_buf();
+    };
+
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(rest),
+        None => path.to_path_buf(),
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{Result, eyre::WrapErr};
+
+/// Where yanked text is written in the data directory when no clipboard is available.
+const FALLBACK_FILE: &str = "yank.txt";
+
+/// Where [`copy`] put the text.
+pub enum Copied {
+    /// Handed to the terminal, which may ignore it if it doesn't support OSC 52.
+    Terminal,
+    File(PathBuf),
+}
+
+/// Sends `text` to the terminal's clipboard, or writes it to a file in `data_dir` when there
+/// is no terminal to ask or the `clipboard` feature is off. The data directory belongs to
+/// the user, unlike the shared temp directory.
+pub fn copy(text: &str, data_dir: &Path) -> Result<Copied> {
+    if to_clipboard(text) {
+        return Ok(Copied::Terminal);
+    }
+    fs::create_dir_all(data_dir)
+        .wrap_err_with(|| format!("Failed to create {}", data_dir.display()))?;
+    let path = data_dir.join(FALLBACK_FILE);
+    fs::write(&path, text).wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    Ok(Copied::File(path))
+}
+
+/// Asks the terminal to set the clipboard with an OSC 52 escape sequence, which also works
+/// over SSH. A dumb or missing terminal can't be asked.
+#[cfg(feature = "clipboard")]
+fn to_clipboard(text: &str) -> bool {
+    use std::env;
+
+    use crossterm::{clipboard::CopyToClipboard, execute};
+
+    if env::var("TERM").is_ok_and(|term| !term.is_empty() && term != "dumb") {
+        execute!(std::io::stdout(), CopyToClipboard::to_clipboard_from(text)).is_ok()
+    } else {
+        false
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn to_clipboard(_text: &str) -> bool {
+    false
+}
+
+#[cfg(all(test, not(feature = "clipboard")))]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn falls_back_to_a_file_in_the_data_dir() {
+        let data_dir = env::temp_dir().join(format!("task-manager-tui-yank-{}", process::id()));
+        let Copied::File(path) = copy("call the bank", &data_dir).unwrap() else {
+            panic!("copied to a clipboard without the feature");
+        };
+        let copied = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&data_dir).unwrap();
+        assert_eq!(path, data_dir.join(FALLBACK_FILE));
+        assert_eq!(copied, "call the bank");
+    }
+}
@@ -0,0 +1,84 @@
+use color_eyre::{Result, eyre::bail};
+
+use crate::model::SortMode;
+
+/// A command typed on the `:` command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Write,
+    Quit,
+    WriteQuit,
+    Sort(SortMode),
+    /// Whether completed tasks should be hidden.
+    HideDone(bool),
+    /// Deletes the task on this 1-based row of the current view.
+    Delete(usize),
+    ClearCompleted,
+    /// Opens the task list with this name, creating it if needed.
+    List(String),
+    /// Blocks the selected task until the task on this 1-based row is done.
+    Block(usize),
+    Unblock,
+    /// Renumbers task positions without gaps.
+    Compact,
+}
+
+impl Command {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut words = input.split_whitespace();
+        let Some(name) = words.next() else {
+            bail!("Empty command");
+        };
+        let argument = words.next();
+        if words.next().is_some() {
+            bail!("Too many arguments for :{}", name);
+        }
+
+        let command = match (name, argument) {
+            ("w", None) => Command::Write,
+            ("q", None) => Command::Quit,
+            ("wq" | "x", None) => Command::WriteQuit,
+            ("sort", Some(mode)) => match SortMode::from_name(mode) {
+                Some(mode) => Command::Sort(mode),
+                None => bail!(
+                    "Unknown sort mode '{}', expected manual, id, priority, alpha, status, modified or done-last",
+                    mode
+                ),
+            },
+            ("filter", Some("done")) => Command::HideDone(true),
+            ("filter", Some("none" | "all")) => Command::HideDone(false),
+            ("filter", Some(filter)) => {
+                bail!("Unknown filter '{}', expected done or none", filter)
+            }
+            ("delete", Some(row)) => match row.parse() {
+                Ok(row) if row > 0 => Command::Delete(row),
+                _ => bail!("Invalid task number '{}'", row),
+            },
+            ("block", Some(row)) => match row.parse() {
+                Ok(row) if row > 0 => Command::Block(row),
+                _ => bail!("Invalid task number '{}'", row),
+            },
+            ("unblock", None) => Command::Unblock,
+            ("clear", None) => Command::ClearCompleted,
+            ("compact", None) => Command::Compact,
+            ("list", Some(list)) => {
+                // The name ends up in a file name.
+                if !list
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    bail!("List names may only use letters, digits, - and _");
+                }
+                Command::List(list.to_string())
+            }
+            ("sort" | "filter" | "delete" | "list" | "block", None) => {
+                bail!(":{} needs an argument", name)
+            }
+            ("w" | "q" | "wq" | "x" | "clear" | "compact" | "unblock", Some(_)) => {
+                bail!(":{} takes no arguments", name)
+            }
+            _ => bail!("Unknown command :{}", name),
+        };
+        Ok(command)
+    }
+}
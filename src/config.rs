@@ -0,0 +1,232 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use color_eyre::{Result, eyre::WrapErr};
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer, de::Error};
+
+use crate::model::{Priority, SortMode};
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Settings read from `config.toml` next to the database. Every field is optional.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Whether to list overdue and due-today tasks when the app starts.
+    pub reminders: bool,
+    /// The longest task description, in characters, that can be saved.
+    pub max_description_length: usize,
+    /// Days after which an unfinished task is shown as stale. Zero turns this off.
+    pub stale_after_days: u32,
+    /// Whether rows start with their 1-based number in the list, as used by `:delete`.
+    pub line_numbers: bool,
+    /// How many loaded tasks it takes for the footer to suggest archiving or filtering. Zero
+    /// turns this off.
+    pub large_list_warning: usize,
+    /// How many tasks to load at a time, in a window that follows the selection through the
+    /// list. Zero loads every task.
+    pub load_window: usize,
+    /// How many days, ending today, the statistics chart of completions covers. Zero hides
+    /// the chart.
+    pub chart_days: usize,
+    /// The sort mode the list opens in, as accepted by `:sort`.
+    #[serde(deserialize_with = "sort_mode")]
+    pub default_sort: Option<SortMode>,
+    /// The filter the list opens with.
+    pub default_filter: Option<StartupFilter>,
+    pub theme: Theme,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reminders: true,
+            max_description_length: 200,
+            stale_after_days: 7,
+            line_numbers: false,
+            large_list_warning: 500,
+            load_window: 0,
+            chart_days: 14,
+            default_sort: None,
+            default_filter: None,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// A filter that can be switched on at startup, named like its key binding.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupFilter {
+    HideDone,
+    Today,
+    FilterHigh,
+    FilterNormal,
+    FilterLow,
+}
+
+impl StartupFilter {
+    /// The priority shown when this is one of the priority filters.
+    pub fn priority(self) -> Option<Priority> {
+        match self {
+            StartupFilter::FilterHigh => Some(Priority::High),
+            StartupFilter::FilterNormal => Some(Priority::Normal),
+            StartupFilter::FilterLow => Some(Priority::Low),
+            StartupFilter::HideDone | StartupFilter::Today => None,
+        }
+    }
+}
+
+fn sort_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SortMode>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    SortMode::from_name(&name)
+        .map(Some)
+        .ok_or_else(|| D::Error::custom(format!("unknown sort mode `{}`", name)))
+}
+
+impl Config {
+    /// Loads the config from `data_dir`, using the defaults when the file does not exist.
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(CONFIG_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("cannot read {}", path.display()));
+            }
+        };
+        toml::from_str(&contents).wrap_err_with(|| format!("invalid {}", path.display()))
+    }
+}
+
+/// The built-in palettes cycled with `t`. `[theme]` in the config customizes `Dark`.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ThemeName {
+    pub fn next(self) -> Self {
+        match self {
+            ThemeName::Dark => ThemeName::Light,
+            ThemeName::Light => ThemeName::HighContrast,
+            ThemeName::HighContrast => ThemeName::Dark,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [ThemeName::Dark, ThemeName::Light, ThemeName::HighContrast]
+            .into_iter()
+            .find(|name| name.label() == label)
+    }
+}
+
+/// Colors used when rendering. Values accept names like `"cyan"` or hex like `"#ff8800"`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub border: Color,
+    pub highlight: Color,
+    pub done: Color,
+    pub overdue: Color,
+    pub due_today: Color,
+    pub doing: Color,
+    pub stale: Color,
+    pub tag: Color,
+    pub muted: Color,
+    pub info: Color,
+    pub error: Color,
+    pub priority: PriorityColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border: Color::Cyan,
+            highlight: Color::Yellow,
+            done: Color::DarkGray,
+            overdue: Color::Red,
+            due_today: Color::Yellow,
+            doing: Color::LightBlue,
+            stale: Color::Rgb(0xff, 0x87, 0x00),
+            tag: Color::Magenta,
+            muted: Color::DarkGray,
+            info: Color::Green,
+            error: Color::Red,
+            priority: PriorityColors::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            border: Color::Blue,
+            highlight: Color::Magenta,
+            done: Color::Gray,
+            overdue: Color::Red,
+            due_today: Color::Rgb(0xaf, 0x87, 0x00),
+            doing: Color::Blue,
+            stale: Color::Rgb(0xaf, 0x5f, 0x00),
+            tag: Color::Rgb(0x00, 0x87, 0x87),
+            muted: Color::Gray,
+            info: Color::Green,
+            error: Color::Red,
+            priority: PriorityColors {
+                high: Color::Red,
+                normal: Color::Black,
+                low: Color::DarkGray,
+            },
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            border: Color::White,
+            highlight: Color::LightYellow,
+            done: Color::Gray,
+            overdue: Color::LightRed,
+            due_today: Color::LightYellow,
+            doing: Color::LightBlue,
+            stale: Color::LightCyan,
+            tag: Color::LightMagenta,
+            muted: Color::Gray,
+            info: Color::LightGreen,
+            error: Color::LightRed,
+            priority: PriorityColors {
+                high: Color::LightRed,
+                normal: Color::White,
+                low: Color::Gray,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityColors {
+    pub high: Color,
+    pub normal: Color,
+    pub low: Color,
+}
+
+impl Default for PriorityColors {
+    fn default() -> Self {
+        Self {
+            high: Color::Red,
+            normal: Color::White,
+            low: Color::White,
+        }
+    }
+}
@@ -0,0 +1,1687 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use rusqlite::{Connection, OptionalExtension, Transaction};
+
+use crate::{
+    export::ImportedTask,
+    model::{
+        Activity, ActivityEntry, Priority, Recurrence, SortMode, TagStats, TaskColor, TaskCounts,
+        TaskStatus, TodoItem, format_due, parse_due,
+    },
+};
+
+/// How long to wait on a database locked by another process before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The name of the list kept in the database the app was opened on.
+pub const DEFAULT_LIST: &str = "default";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Skip entries whose description matches an existing task.
+    Merge,
+    /// Delete every existing task before importing.
+    Replace,
+}
+
+/// Opens the database at `db_path`, creating its directory if needed.
+pub fn open(db_path: &Path) -> Result<Connection> {
+    if let Some(parent) = db_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).wrap_err_with(|| {
+            format!("failed to create database directory {}", parent.display())
+        })?;
+    }
+
+    let connection = Connection::open(db_path)
+        .wrap_err_with(|| format!("failed to open database at {}", db_path.display()))?;
+    connection.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(connection)
+}
+
+/// Switches to write-ahead logging so other tools can read while the app writes.
+pub fn enable_wal(connection: &Connection) -> Result<()> {
+    let mode: String =
+        connection.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        bail!("journal mode is {}", mode);
+    }
+    Ok(())
+}
+
+pub fn init_schema(connection: &Connection) -> Result<()> {
+    connection.execute_batch("PRAGMA foreign_keys = ON")?;
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            description TEXT NOT NULL,
+            is_done INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    add_column_if_missing(
+        connection,
+        "tasks",
+        "priority",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    add_column_if_missing(connection, "tasks", "due_date", "TEXT")?;
+    if add_column_if_missing(
+        connection,
+        "tasks",
+        "position",
+        "INTEGER NOT NULL DEFAULT 0",
+    )? {
+        connection.execute("UPDATE tasks SET position = id", [])?;
+    }
+    add_column_if_missing(connection, "tasks", "created_at", "TEXT")?;
+    add_column_if_missing(connection, "tasks", "completed_at", "TEXT")?;
+    add_column_if_missing(
+        connection,
+        "tasks",
+        "archived",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        connection,
+        "tasks",
+        "recurrence",
+        "TEXT NOT NULL DEFAULT 'none'",
+    )?;
+    add_column_if_missing(connection, "tasks", "notes", "TEXT")?;
+    add_column_if_missing(connection, "tasks", "category", "TEXT")?;
+    // `is_done` stays the completion flag every query filters on; `status` adds "doing".
+    if add_column_if_missing(connection, "tasks", "status", "INTEGER NOT NULL DEFAULT 0")? {
+        connection.execute("UPDATE tasks SET status = 2 WHERE is_done = 1", [])?;
+    }
+    if add_column_if_missing(connection, "tasks", "updated_at", "TEXT")? {
+        connection.execute(
+            "UPDATE tasks SET updated_at = COALESCE(created_at, ?1)",
+            [format_timestamp(Utc::now())],
+        )?;
+    }
+    add_column_if_missing(connection, "tasks", "color", "TEXT NOT NULL DEFAULT 'none'")?;
+    // No foreign key: subtasks of a deleted parent show at the top level, and undoing the
+    // delete puts the parent back over them under the same id.
+    add_column_if_missing(connection, "tasks", "parent_id", "INTEGER")?;
+    add_column_if_missing(connection, "tasks", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+    // No foreign key either: a deleted blocker no longer blocks anything, and undoing the
+    // delete blocks its dependents again.
+    add_column_if_missing(connection, "tasks", "blocked_by", "INTEGER")?;
+
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            PRIMARY KEY (task_id, name)
+        )",
+        [],
+    )?;
+
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Only read in the database the app was opened on, which names the other lists.
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS lists (
+            name TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Entries copy the description instead of referencing the task, so they outlive it.
+    connection.execute(
+        "CREATE TABLE IF NOT EXISTS activity_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            action TEXT NOT NULL,
+            description TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn add_column_if_missing(
+    connection: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<bool> {
+    let mut stmt = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if !exists {
+        connection.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(!exists)
+}
+
+/// Where the list `name` is kept: a database file beside `home`, the one the app was
+/// opened on, named after both, like `tasks-work.db`.
+pub fn list_path(home: &Path, name: &str) -> PathBuf {
+    let stem = home.file_stem().unwrap_or_default().to_string_lossy();
+    home.with_file_name(format!("{}-{}.db", stem, name))
+}
+
+/// The lists created besides the default one, by name.
+pub fn list_names(connection: &Connection) -> Result<Vec<String>> {
+    let mut stmt = connection.prepare("SELECT name FROM lists ORDER BY name")?;
+    let names = stmt.query_map([], |row| row.get(0))?;
+    Ok(names.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Records the list `name`, doing nothing if it exists.
+pub fn add_list(connection: &Connection, name: &str, now: DateTime<Utc>) -> Result<()> {
+    connection.execute(
+        "INSERT OR IGNORE INTO lists (name, created_at) VALUES (?1, ?2)",
+        rusqlite::params![name, format_timestamp(now)],
+    )?;
+    Ok(())
+}
+
+/// Runs `f` in a transaction that commits only if it returns `Ok`; on error every statement
+/// it executed is rolled back.
+pub fn with_transaction<T>(
+    connection: &mut Connection,
+    f: impl FnOnce(&Transaction) -> Result<T>,
+) -> Result<T> {
+    let tx = connection.transaction()?;
+    let value = f(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+/// Pinned tasks come first. The rest are grouped by category, uncategorized last, and sorted
+/// by `sort_mode` within each group, which pinned tasks are sorted by too.
+fn order_by(sort_mode: SortMode) -> String {
+    let within = match sort_mode {
+        SortMode::Manual => "position ASC, id ASC",
+        SortMode::Id => "id ASC",
+        SortMode::Priority => "is_done ASC, priority DESC, id ASC",
+        SortMode::Alphabetical => "description COLLATE NOCASE ASC, id ASC",
+        SortMode::Status => "is_done ASC, status DESC, id ASC",
+        SortMode::Modified => "updated_at DESC, id DESC",
+        SortMode::DoneLast => "is_done ASC, position ASC, id ASC",
+    };
+    format!(
+        "pinned DESC, CASE WHEN pinned = 0 THEN category IS NULL END,
+         CASE WHEN pinned = 0 THEN category COLLATE NOCASE END, {}",
+        within
+    )
+}
+
+const TASK_COLUMNS: &str = "id, description, is_done, priority, due_date, position, created_at,
+    completed_at, (SELECT GROUP_CONCAT(name, ' ') FROM tags WHERE task_id = tasks.id), archived,
+    recurrence, notes, category, status, updated_at, color, parent_id, pinned, blocked_by,
+    COALESCE((SELECT NOT is_done FROM tasks AS blocker WHERE blocker.id = tasks.blocked_by), 0)";
+
+/// Loads the tasks in or out of the archive, or every task when `archived` is `None`, in
+/// `sort_mode` order.
+pub fn load_tasks(
+    connection: &Connection,
+    archived: Option<bool>,
+    sort_mode: SortMode,
+) -> Result<Vec<TodoItem>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM tasks WHERE ?1 IS NULL OR archived = ?1 ORDER BY {}",
+        TASK_COLUMNS,
+        order_by(sort_mode)
+    ))?;
+    let items = stmt.query_map([archived.map(|archived| archived as i32)], task_from_row)?;
+    Ok(nest_subtasks(items.collect::<rusqlite::Result<_>>()?))
+}
+
+/// Loads at most `limit` tasks in or out of the archive, starting `offset` tasks into the
+/// `sort_mode` order. Subtasks are only nested under a parent in the same window.
+pub fn load_task_window(
+    connection: &Connection,
+    archived: bool,
+    sort_mode: SortMode,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<TodoItem>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM tasks WHERE archived = ?1 ORDER BY {} LIMIT ?2 OFFSET ?3",
+        TASK_COLUMNS,
+        order_by(sort_mode)
+    ))?;
+    let items = stmt.query_map(
+        rusqlite::params![archived as i32, limit as i64, offset as i64],
+        task_from_row,
+    )?;
+    Ok(nest_subtasks(items.collect::<rusqlite::Result<_>>()?))
+}
+
+/// Where the task `id` falls in the `sort_mode` order of the tasks in or out of the archive,
+/// counted from zero.
+pub fn task_position(
+    connection: &Connection,
+    archived: bool,
+    sort_mode: SortMode,
+    id: i64,
+) -> Result<Option<usize>> {
+    let position: Option<i64> = connection
+        .query_row(
+            &format!(
+                "SELECT row FROM (
+                    SELECT id, ROW_NUMBER() OVER (ORDER BY {}) - 1 AS row
+                    FROM tasks WHERE archived = ?1
+                 ) WHERE id = ?2",
+                order_by(sort_mode)
+            ),
+            rusqlite::params![archived as i32, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(position.map(|position| position as usize))
+}
+
+/// Counts the tasks in or out of the archive. Unfinished tasks added before `stale_before`
+/// are counted as stale.
+pub fn task_counts(
+    connection: &Connection,
+    archived: bool,
+    stale_before: Option<DateTime<Utc>>,
+) -> Result<TaskCounts> {
+    let (total, done, stale): (i64, i64, i64) = connection.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(is_done), 0),
+                COALESCE(SUM(is_done = 0 AND ?2 IS NOT NULL AND created_at <= ?2), 0)
+         FROM tasks WHERE archived = ?1",
+        rusqlite::params![archived as i32, stale_before.map(format_timestamp)],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    Ok(TaskCounts {
+        total: total as usize,
+        done: done as usize,
+        stale: stale as usize,
+    })
+}
+
+fn task_from_row(row: &rusqlite::Row) -> rusqlite::Result<TodoItem> {
+    let due = row
+        .get::<_, Option<String>>(4)?
+        .and_then(|due| parse_due(&due));
+    Ok(TodoItem {
+        id: row.get(0)?,
+        description: row.get(1)?,
+        is_done: row.get::<_, i32>(2)? != 0,
+        status: TaskStatus::from_i64(row.get(13)?),
+        priority: Priority::from_i64(row.get(3)?),
+        due_date: due.map(|(date, _)| date),
+        due_time: due.and_then(|(_, time)| time),
+        position: row.get(5)?,
+        created_at: row
+            .get::<_, Option<String>>(6)?
+            .and_then(|ts| parse_timestamp(&ts)),
+        completed_at: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|ts| parse_timestamp(&ts)),
+        tags: {
+            let mut tags: Vec<String> = row
+                .get::<_, Option<String>>(8)?
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            tags.sort();
+            tags
+        },
+        archived: row.get::<_, i32>(9)? != 0,
+        recurrence: Recurrence::from_label(&row.get::<_, String>(10)?),
+        color: TaskColor::from_label(&row.get::<_, String>(15)?),
+        notes: row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+        category: row.get(12)?,
+        parent_id: row.get(16)?,
+        pinned: row.get::<_, i32>(17)? != 0,
+        blocked_by: row.get(18)?,
+        blocked: row.get::<_, i32>(19)? != 0,
+        updated_at: row
+            .get::<_, Option<String>>(14)?
+            .and_then(|ts| parse_timestamp(&ts)),
+    })
+}
+
+/// Moves each subtask right after its parent, keeping the sorted order among siblings.
+/// Subtasks whose parent isn't loaded keep their place as top-level tasks.
+fn nest_subtasks(items: Vec<TodoItem>) -> Vec<TodoItem> {
+    let ids: HashSet<i64> = items.iter().map(|item| item.id).collect();
+    let (children, roots): (Vec<TodoItem>, Vec<TodoItem>) = items
+        .into_iter()
+        .partition(|item| item.parent_id.is_some_and(|parent| ids.contains(&parent)));
+    let mut children: Vec<Option<TodoItem>> = children.into_iter().map(Some).collect();
+
+    let mut nested = Vec::with_capacity(ids.len());
+    for root in roots {
+        let id = root.id;
+        nested.push(root);
+        for slot in &mut children {
+            if slot
+                .as_ref()
+                .is_some_and(|child| child.parent_id == Some(id))
+            {
+                nested.extend(slot.take());
+            }
+        }
+    }
+    // Anything nested deeper than one level is shown at the end rather than dropped.
+    nested.extend(children.into_iter().flatten());
+    nested
+}
+
+/// Done and total counts per tag in or out of the archive, with untagged tasks bucketed
+/// together at the end.
+pub fn tag_stats(connection: &Connection, archived: bool) -> Result<Vec<TagStats>> {
+    let mut stmt = connection.prepare(
+        "SELECT tags.name, COUNT(*), SUM(tasks.is_done)
+         FROM tasks LEFT JOIN tags ON tags.task_id = tasks.id
+         WHERE tasks.archived = ?1
+         GROUP BY tags.name
+         ORDER BY tags.name IS NULL, tags.name",
+    )?;
+    let stats = stmt.query_map([archived as i32], |row| {
+        Ok(TagStats {
+            name: row
+                .get::<_, Option<String>>(0)?
+                .unwrap_or_else(|| "(untagged)".to_string()),
+            total: row.get(1)?,
+            done: row.get(2)?,
+        })
+    })?;
+    Ok(stats.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Every tag on a task in or out of the archive.
+pub fn tag_names(connection: &Connection, archived: bool) -> Result<Vec<String>> {
+    let mut stmt = connection.prepare(
+        "SELECT DISTINCT tags.name FROM tags JOIN tasks ON tasks.id = tags.task_id
+         WHERE tasks.archived = ?1 ORDER BY tags.name",
+    )?;
+    let names = stmt.query_map([archived as i32], |row| row.get(0))?;
+    Ok(names.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Up to `limit` distinct tags, archived tasks included, that start with `prefix` without
+/// being it, for completing a tag as it's typed.
+pub fn tags_starting_with(
+    connection: &Connection,
+    prefix: &str,
+    limit: usize,
+) -> Result<Vec<String>> {
+    let mut stmt = connection.prepare(
+        "SELECT DISTINCT name FROM tags
+         WHERE substr(name, 1, length(?1)) = ?1 AND name != ?1
+         ORDER BY name LIMIT ?2",
+    )?;
+    let names = stmt.query_map(rusqlite::params![prefix, limit as i64], |row| row.get(0))?;
+    Ok(names.collect::<rusqlite::Result<_>>()?)
+}
+
+/// When every completed task, archived or not, was completed.
+pub fn completion_times(connection: &Connection) -> Result<Vec<DateTime<Utc>>> {
+    let mut stmt = connection
+        .prepare("SELECT completed_at FROM tasks WHERE is_done = 1 AND completed_at IS NOT NULL")?;
+    let times = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .iter()
+        .filter_map(|value| parse_timestamp(value))
+        .collect();
+    Ok(times)
+}
+
+/// Incomplete tasks outside the archive.
+pub fn remaining_count(connection: &Connection) -> Result<i64> {
+    let count = connection.query_row(
+        "SELECT COUNT(*) FROM tasks WHERE is_done = 0 AND archived = 0",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// Whether an incomplete, unarchived task already has `description`, ignoring surrounding
+/// whitespace and case. Case is folded here rather than in SQL, whose `lower` only knows
+/// ASCII letters.
+pub fn has_incomplete_duplicate(connection: &Connection, description: &str) -> Result<bool> {
+    let wanted = description.trim().to_lowercase();
+    let mut stmt =
+        connection.prepare("SELECT description FROM tasks WHERE is_done = 0 AND archived = 0")?;
+    for existing in stmt.query_map([], |row| row.get::<_, String>(0))? {
+        if existing?.trim().to_lowercase() == wanted {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Inserts a new incomplete task, a subtask of `parent_id` when given, at the end of the
+/// manual order, logs it as added and returns its id.
+pub fn add_task(
+    connection: &Connection,
+    description: &str,
+    tags: &[String],
+    category: Option<&str>,
+    parent_id: Option<i64>,
+    now: DateTime<Utc>,
+) -> Result<i64> {
+    connection.execute(
+        "INSERT INTO tasks (
+            description, is_done, priority, position, created_at, updated_at, category,
+            parent_id
+         ) VALUES (
+            ?1, 0, ?2, (SELECT COALESCE(MAX(position), 0) + 1 FROM tasks), ?3, ?3, ?4, ?5
+         )",
+        rusqlite::params![
+            description,
+            Priority::default().as_i64(),
+            format_timestamp(now),
+            category,
+            parent_id
+        ],
+    )?;
+
+    let id = connection.last_insert_rowid();
+    insert_tags(connection, id, tags)?;
+    log_activity(connection, Activity::Added, description, now)?;
+    Ok(id)
+}
+
+/// Copies `item`, tags, notes, due date and recurrence included, into a new incomplete task
+/// just below it and returns the id of the copy.
+pub fn duplicate_task(connection: &Connection, item: &TodoItem, now: DateTime<Utc>) -> Result<i64> {
+    connection.execute(
+        "UPDATE tasks SET position = position + 1 WHERE position > ?1",
+        [item.position],
+    )?;
+    connection.execute(
+        "INSERT INTO tasks (
+            description, is_done, priority, due_date, position, created_at, updated_at,
+            archived, recurrence, notes, category, color, parent_id, pinned, blocked_by
+         ) VALUES (?1, 0, ?2, ?3, ?4, ?5, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        rusqlite::params![
+            item.description,
+            item.priority.as_i64(),
+            item.due_label(),
+            item.position + 1,
+            format_timestamp(now),
+            item.archived,
+            item.recurrence.label(),
+            Some(item.notes.as_str()).filter(|notes| !notes.is_empty()),
+            item.category,
+            item.color.label(),
+            item.parent_id,
+            item.pinned as i32,
+            item.blocked_by,
+        ],
+    )?;
+    let id = connection.last_insert_rowid();
+    insert_tags(connection, id, &item.tags)?;
+    Ok(id)
+}
+
+/// Puts a deleted task back with its original id, position and tags.
+pub fn restore_task(connection: &Connection, item: &TodoItem) -> Result<()> {
+    connection.execute(
+        "INSERT INTO tasks (
+            id, description, is_done, priority, due_date, position,
+            created_at, completed_at, archived, recurrence, notes, category, status, updated_at,
+            color, parent_id, pinned, blocked_by
+         ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18
+         )",
+        rusqlite::params![
+            item.id,
+            item.description,
+            item.is_done as i32,
+            item.priority.as_i64(),
+            item.due_label(),
+            item.position,
+            item.created_at.map(format_timestamp),
+            item.completed_at.map(format_timestamp),
+            item.archived as i32,
+            item.recurrence.label(),
+            Some(item.notes.as_str()).filter(|notes| !notes.is_empty()),
+            item.category,
+            item.status.as_i64(),
+            item.updated_at.map(format_timestamp),
+            item.color.label(),
+            item.parent_id,
+            item.pinned as i32,
+            item.blocked_by,
+        ],
+    )?;
+    insert_tags(connection, item.id, &item.tags)
+}
+
+/// Inserts `tasks` and returns how many rows were added. Callers wrap this in a
+/// transaction so a failure part way through inserts nothing. Subtask and blocker links
+/// are pointed at the new ids; links to tasks that weren't imported are dropped.
+pub fn import_tasks(
+    connection: &Connection,
+    tasks: &[ImportedTask],
+    mode: ImportMode,
+) -> Result<usize> {
+    if mode == ImportMode::Replace {
+        connection.execute("DELETE FROM tasks", [])?;
+    }
+
+    let mut existing: HashSet<String> = HashSet::new();
+    if mode == ImportMode::Merge {
+        let mut stmt = connection.prepare("SELECT description FROM tasks")?;
+        for description in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            existing.insert(description?.to_lowercase());
+        }
+    }
+
+    let mut new_ids: HashMap<i64, i64> = HashMap::new();
+    let mut imported = 0;
+    for task in tasks {
+        if !existing.insert(task.description.to_lowercase()) {
+            continue;
+        }
+        connection.execute(
+            "INSERT INTO tasks (
+                description, is_done, priority, due_date, position, created_at,
+                completed_at, archived, recurrence, notes, category, status, updated_at, color,
+                pinned
+             ) VALUES (
+                ?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(position), 0) + 1 FROM tasks), ?5,
+                ?6, ?7, ?8, ?9, ?10, ?11, COALESCE(?12, ?5), ?13, ?14
+             )",
+            rusqlite::params![
+                task.description,
+                task.is_done as i32,
+                task.priority.as_i64(),
+                task.due_date.map(|date| format_due(date, task.due_time)),
+                task.created_at.map(format_timestamp),
+                task.completed_at.map(format_timestamp),
+                task.archived as i32,
+                task.recurrence.label(),
+                Some(task.notes.as_str()).filter(|notes| !notes.is_empty()),
+                task.category,
+                match task.status {
+                    _ if task.is_done => TaskStatus::Done,
+                    TaskStatus::Done => TaskStatus::Todo,
+                    status => status,
+                }
+                .as_i64(),
+                task.updated_at.map(format_timestamp),
+                task.color.label(),
+                task.pinned as i32,
+            ],
+        )?;
+        let id = connection.last_insert_rowid();
+        insert_tags(connection, id, &task.tags)?;
+        if let Some(old_id) = task.id {
+            new_ids.insert(old_id, id);
+        }
+        imported += 1;
+    }
+
+    for task in tasks {
+        let Some(id) = task.id.and_then(|old_id| new_ids.get(&old_id)) else {
+            continue;
+        };
+        let relink = |link: Option<i64>| link.and_then(|old_id| new_ids.get(&old_id));
+        connection.execute(
+            "UPDATE tasks SET parent_id = ?1, blocked_by = ?2 WHERE id = ?3",
+            rusqlite::params![relink(task.parent_id), relink(task.blocked_by), id],
+        )?;
+    }
+    Ok(imported)
+}
+
+/// Replaces the description, tags and category of a task.
+pub fn update_task(
+    connection: &Connection,
+    id: i64,
+    description: &str,
+    tags: &[String],
+    category: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET description = ?1, category = ?2, updated_at = ?3 WHERE id = ?4",
+        rusqlite::params![description, category, format_timestamp(now), id],
+    )?;
+    connection.execute("DELETE FROM tags WHERE task_id = ?1", [id])?;
+    insert_tags(connection, id, tags)
+}
+
+pub fn set_priority(
+    connection: &Connection,
+    id: i64,
+    priority: Priority,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET priority = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![priority.as_i64(), format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+pub fn set_pinned(
+    connection: &Connection,
+    id: i64,
+    pinned: bool,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET pinned = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![pinned as i32, format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+/// Makes task `id` wait on `blocker`, or on nothing when `None`. Fails when `blocker` is
+/// itself waiting on `id`, directly or through other tasks, since neither could be done.
+pub fn set_blocked_by(
+    connection: &Connection,
+    id: i64,
+    blocker: Option<i64>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    if let Some(blocker) = blocker {
+        let cycle: bool = connection.query_row(
+            "WITH RECURSIVE chain(id) AS (
+                SELECT ?1
+                UNION SELECT tasks.blocked_by FROM tasks JOIN chain ON tasks.id = chain.id
+             )
+             SELECT EXISTS(SELECT 1 FROM chain WHERE id = ?2)",
+            [blocker, id],
+            |row| row.get(0),
+        )?;
+        if cycle {
+            bail!("A task can't wait on one that is waiting on it");
+        }
+    }
+    connection.execute(
+        "UPDATE tasks SET blocked_by = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![blocker, format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+pub fn set_color(
+    connection: &Connection,
+    id: i64,
+    color: TaskColor,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET color = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![color.label(), format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+pub fn set_recurrence(
+    connection: &Connection,
+    id: i64,
+    recurrence: Recurrence,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET recurrence = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![recurrence.label(), format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+/// Saves `notes`, storing empty notes as NULL.
+pub fn set_notes(connection: &Connection, id: i64, notes: &str, now: DateTime<Utc>) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET notes = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![
+            Some(notes).filter(|notes| !notes.is_empty()),
+            format_timestamp(now),
+            id
+        ],
+    )?;
+    Ok(())
+}
+
+/// Sets or clears the due date, stored with its time when `due_time` is given.
+pub fn set_due_date(
+    connection: &Connection,
+    id: i64,
+    due_date: Option<NaiveDate>,
+    due_time: Option<NaiveTime>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET due_date = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![
+            due_date.map(|date| format_due(date, due_time)),
+            format_timestamp(now),
+            id
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn set_archived(
+    connection: &Connection,
+    id: i64,
+    archived: bool,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET archived = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![archived as i32, format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+pub fn set_position(connection: &Connection, id: i64, position: i64) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET position = ?1 WHERE id = ?2",
+        rusqlite::params![position, id],
+    )?;
+    Ok(())
+}
+
+/// Moves a task before every other task in the manual order.
+pub fn move_to_top(connection: &Connection, id: i64) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET position = (SELECT MIN(position) FROM tasks) - 1 WHERE id = ?1",
+        [id],
+    )?;
+    renumber_positions(connection)?;
+    Ok(())
+}
+
+/// Moves a task after every other task in the manual order.
+pub fn move_to_bottom(connection: &Connection, id: i64) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET position = (SELECT MAX(position) FROM tasks) + 1 WHERE id = ?1",
+        [id],
+    )?;
+    renumber_positions(connection)?;
+    Ok(())
+}
+
+/// Rewrites positions as 1, 2, 3... keeping the current manual order, which closes the gaps
+/// deletes leave, and returns how many tasks moved.
+pub fn renumber_positions(connection: &Connection) -> Result<usize> {
+    let moved = connection.execute(
+        "UPDATE tasks SET position = ranked.position
+         FROM (
+            SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS position FROM tasks
+         ) AS ranked
+         WHERE tasks.id = ranked.id AND tasks.position != ranked.position",
+        [],
+    )?;
+    Ok(moved)
+}
+
+/// Deletes a task; its tags go with it.
+pub fn delete_task(connection: &Connection, id: i64) -> Result<()> {
+    connection.execute("DELETE FROM tasks WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Every completed task in or out of the archive, in id order, loaded or not.
+pub fn completed_tasks(connection: &Connection, archived: bool) -> Result<Vec<TodoItem>> {
+    let mut stmt = connection.prepare(&format!(
+        "SELECT {} FROM tasks WHERE is_done = 1 AND archived = ?1 ORDER BY id",
+        TASK_COLUMNS
+    ))?;
+    let items = stmt.query_map([archived as i32], task_from_row)?;
+    Ok(items.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Deletes every completed task in or out of the archive and returns how many were removed.
+pub fn delete_completed(connection: &Connection, archived: bool) -> Result<usize> {
+    let removed = connection.execute(
+        "DELETE FROM tasks WHERE is_done = 1 AND archived = ?1",
+        [archived as i32],
+    )?;
+    Ok(removed)
+}
+
+/// Sets the completion state of a task, returning its new `completed_at`.
+pub fn set_done(
+    connection: &Connection,
+    id: i64,
+    done: bool,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>> {
+    let completed_at = done.then_some(now);
+    connection.execute(
+        "UPDATE tasks SET
+            is_done = ?1,
+            status = CASE WHEN ?1 = 1 THEN 2 ELSE 0 END,
+            completed_at = ?2,
+            updated_at = ?3
+         WHERE id = ?4",
+        rusqlite::params![
+            done as i32,
+            completed_at.map(format_timestamp),
+            format_timestamp(now),
+            id
+        ],
+    )?;
+    Ok(completed_at)
+}
+
+/// Moves a task to `status`, keeping `is_done` in step, and returns its new `completed_at`.
+/// A task that was already done keeps its completion time.
+pub fn set_status(
+    connection: &Connection,
+    id: i64,
+    status: TaskStatus,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>> {
+    let done = status == TaskStatus::Done;
+    let completed_at = connection
+        .query_row(
+            "UPDATE tasks SET
+            is_done = ?1,
+            status = ?2,
+            completed_at = CASE WHEN ?1 = 0 THEN NULL ELSE COALESCE(completed_at, ?3) END,
+            updated_at = ?3
+         WHERE id = ?4
+         RETURNING completed_at",
+            rusqlite::params![done as i32, status.as_i64(), format_timestamp(now), id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()?;
+    Ok(completed_at.flatten().and_then(|ts| parse_timestamp(&ts)))
+}
+
+/// Sets the completion state of every task in `ids` that isn't already in it, with a single
+/// statement, and returns how many changed.
+pub fn set_done_many(
+    connection: &Connection,
+    ids: &[i64],
+    done: bool,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let completed_at = done.then(|| format_timestamp(now));
+    let params = [
+        rusqlite::types::Value::from(done as i32),
+        rusqlite::types::Value::from(completed_at),
+        rusqlite::types::Value::from(format_timestamp(now)),
+    ]
+    .into_iter()
+    .chain(ids.iter().map(|&id| id.into()));
+    let changed = connection.execute(
+        &format!(
+            "UPDATE tasks SET
+                is_done = ?1,
+                status = CASE WHEN ?1 = 1 THEN 2 ELSE 0 END,
+                completed_at = ?2,
+                updated_at = ?3
+             WHERE is_done != ?1 AND id IN ({})",
+            placeholders
+        ),
+        rusqlite::params_from_iter(params),
+    )?;
+    Ok(changed)
+}
+
+/// Flips completion for a task whose current state the caller doesn't have at hand.
+pub fn flip_done(connection: &Connection, id: i64, now: DateTime<Utc>) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET
+            is_done = 1 - is_done,
+            status = CASE WHEN is_done = 0 THEN 2 ELSE 0 END,
+            completed_at = CASE WHEN is_done = 0 THEN ?1 ELSE NULL END,
+            updated_at = ?1
+         WHERE id = ?2",
+        rusqlite::params![format_timestamp(now), id],
+    )?;
+    Ok(())
+}
+
+/// Marks a recurring task done and archives it, then inserts a fresh copy due at the next
+/// occurrence that carries the recurrence forward. Returns the id of the copy.
+pub fn complete_occurrence(
+    connection: &Connection,
+    item: &TodoItem,
+    now: DateTime<Utc>,
+) -> Result<i64> {
+    connection.execute(
+        "UPDATE tasks SET
+            is_done = 1, status = 2, completed_at = ?1, updated_at = ?1, archived = 1,
+            recurrence = 'none'
+         WHERE id = ?2",
+        rusqlite::params![format_timestamp(now), item.id],
+    )?;
+
+    let today = now.with_timezone(&Local).date_naive();
+    let due_date = item.recurrence.next_due(item.due_date, today);
+    connection.execute(
+        "INSERT INTO tasks (
+            description, is_done, priority, due_date, position, created_at, updated_at,
+            recurrence, notes, category, color, parent_id, pinned, blocked_by
+         ) VALUES (?1, 0, ?2, ?3, ?4, ?5, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        rusqlite::params![
+            item.description,
+            item.priority.as_i64(),
+            due_date.map(|date| format_due(date, item.due_time)),
+            item.position,
+            format_timestamp(now),
+            item.recurrence.label(),
+            Some(item.notes.as_str()).filter(|notes| !notes.is_empty()),
+            item.category,
+            item.color.label(),
+            item.parent_id,
+            item.pinned as i32,
+            item.blocked_by,
+        ],
+    )?;
+    let id = connection.last_insert_rowid();
+    insert_tags(connection, id, &item.tags)?;
+    Ok(id)
+}
+
+/// Reverses `complete_occurrence`: reopens task `id` with the recurrence of `next_id`,
+/// then deletes `next_id`.
+pub fn undo_occurrence(connection: &Connection, id: i64, next_id: i64) -> Result<()> {
+    connection.execute(
+        "UPDATE tasks SET
+            is_done = 0,
+            status = 0,
+            completed_at = NULL,
+            archived = 0,
+            recurrence = (SELECT recurrence FROM tasks WHERE id = ?2)
+         WHERE id = ?1",
+        [id, next_id],
+    )?;
+    connection.execute("DELETE FROM tasks WHERE id = ?1", [next_id])?;
+    Ok(())
+}
+
+fn insert_tags(connection: &Connection, task_id: i64, tags: &[String]) -> Result<()> {
+    let mut stmt =
+        connection.prepare("INSERT OR IGNORE INTO tags (task_id, name) VALUES (?1, ?2)")?;
+    for tag in tags {
+        stmt.execute(rusqlite::params![task_id, tag])?;
+    }
+    Ok(())
+}
+
+pub fn log_activity(
+    connection: &Connection,
+    activity: Activity,
+    description: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO activity_log (action, description, created_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![activity.label(), description, format_timestamp(now)],
+    )?;
+    Ok(())
+}
+
+/// The activity log, newest first.
+pub fn load_activity(connection: &Connection) -> Result<Vec<ActivityEntry>> {
+    let mut stmt = connection
+        .prepare("SELECT action, description, created_at FROM activity_log ORDER BY id DESC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut entries = Vec::new();
+    for row in rows {
+        let (action, description, created_at) = row?;
+        if let (Some(activity), Some(at)) =
+            (Activity::from_label(&action), parse_timestamp(&created_at))
+        {
+            entries.push(ActivityEntry {
+                activity,
+                description,
+                at,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+pub fn read_setting(connection: &Connection, key: &str) -> Result<Option<String>> {
+    let value = connection
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    Ok(value)
+}
+
+pub fn write_setting(connection: &Connection, key: &str, value: &str) -> Result<()> {
+    connection.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [key, value],
+    )?;
+    Ok(())
+}
+
+pub fn delete_setting(connection: &Connection, key: &str) -> Result<()> {
+    connection.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+    Ok(())
+}
+
+/// Every change is already committed; this checkpoints the WAL into the database file.
+pub fn checkpoint(connection: &Connection) -> Result<()> {
+    connection.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")?;
+    Ok(())
+}
+
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        init_schema(&connection).unwrap();
+        connection
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn task_count(connection: &Connection) -> i64 {
+        connection
+            .query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn tag_count(connection: &Connection) -> i64 {
+        connection
+            .query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn init_schema_is_idempotent() {
+        let connection = open();
+        add_task(&connection, "existing", &[], None, None, now()).unwrap();
+
+        init_schema(&connection).unwrap();
+
+        assert_eq!(task_count(&connection), 1);
+    }
+
+    #[test]
+    fn status_migration_maps_done_rows_to_done() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    description TEXT NOT NULL,
+                    is_done INTEGER NOT NULL DEFAULT 0
+                );
+                INSERT INTO tasks (description, is_done) VALUES ('open', 0), ('finished', 1);",
+            )
+            .unwrap();
+
+        init_schema(&connection).unwrap();
+
+        let statuses: Vec<TaskStatus> = load_tasks(&connection, None, SortMode::Id)
+            .unwrap()
+            .iter()
+            .map(|item| item.status)
+            .collect();
+        assert_eq!(statuses, [TaskStatus::Todo, TaskStatus::Done]);
+    }
+
+    #[test]
+    fn updated_at_migration_starts_from_created_at() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE tasks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    description TEXT NOT NULL,
+                    is_done INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT
+                );
+                INSERT INTO tasks (description, created_at)
+                VALUES ('old', '2024-01-05T08:00:00Z'), ('undated', NULL);",
+            )
+            .unwrap();
+
+        init_schema(&connection).unwrap();
+
+        let items = load_tasks(&connection, None, SortMode::Id).unwrap();
+        assert_eq!(items[0].updated_at, items[0].created_at);
+        assert!(items[1].updated_at.is_some());
+    }
+
+    #[test]
+    fn duplicates_keep_the_due_date_and_recurrence() {
+        let connection = open();
+        let id = add_task(&connection, "water plants", &[], None, None, now()).unwrap();
+        let due = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let time = NaiveTime::from_hms_opt(9, 30, 0);
+        set_due_date(&connection, id, Some(due), time, now()).unwrap();
+        set_recurrence(&connection, id, Recurrence::Weekly, now()).unwrap();
+        let item = load_tasks(&connection, None, SortMode::Id)
+            .unwrap()
+            .remove(0);
+
+        let copy_id = duplicate_task(&connection, &item, now()).unwrap();
+        let copy = load_tasks(&connection, None, SortMode::Id)
+            .unwrap()
+            .into_iter()
+            .find(|item| item.id == copy_id)
+            .unwrap();
+        assert_eq!((copy.due_date, copy.due_time), (Some(due), time));
+        assert!(copy.recurrence == Recurrence::Weekly);
+        assert!(!copy.is_done);
+    }
+
+    #[test]
+    fn colors_survive_duplicates_and_unknown_names_load_as_none() {
+        let connection = open();
+        let id = add_task(&connection, "paint fence", &[], None, None, now()).unwrap();
+        set_color(&connection, id, TaskColor::Green, now()).unwrap();
+        let item = load_tasks(&connection, None, SortMode::Id)
+            .unwrap()
+            .remove(0);
+        duplicate_task(&connection, &item, now()).unwrap();
+        connection
+            .execute(
+                "INSERT INTO tasks (description, color) VALUES ('old', 'chartreuse')",
+                [],
+            )
+            .unwrap();
+
+        let colors: Vec<TaskColor> = load_tasks(&connection, None, SortMode::Id)
+            .unwrap()
+            .iter()
+            .map(|item| item.color)
+            .collect();
+        assert_eq!(
+            colors,
+            [TaskColor::Green, TaskColor::Green, TaskColor::None]
+        );
+    }
+
+    #[test]
+    fn subtasks_load_right_after_their_parent() {
+        let connection = open();
+        let parent = add_task(&connection, "move house", &[], None, None, now()).unwrap();
+        add_task(&connection, "pay rent", &[], None, None, now()).unwrap();
+        add_task(&connection, "pack", &[], None, Some(parent), now()).unwrap();
+        let orphan = add_task(&connection, "gone", &[], None, None, now()).unwrap();
+        add_task(&connection, "left behind", &[], None, Some(orphan), now()).unwrap();
+        connection
+            .execute("DELETE FROM tasks WHERE id = ?1", [orphan])
+            .unwrap();
+
+        let order: Vec<String> = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.description)
+            .collect();
+        assert_eq!(order, ["move house", "pack", "pay rent", "left behind"]);
+    }
+
+    #[test]
+    fn renumbering_closes_position_gaps_in_order() {
+        let connection = open();
+        for description in ["first", "gone", "second", "also gone", "third"] {
+            add_task(&connection, description, &[], None, None, now()).unwrap();
+        }
+        connection
+            .execute("DELETE FROM tasks WHERE description LIKE '%gone'", [])
+            .unwrap();
+        connection
+            .execute("UPDATE tasks SET position = position * 10", [])
+            .unwrap();
+
+        assert_eq!(renumber_positions(&connection).unwrap(), 3);
+        let items = load_tasks(&connection, None, SortMode::Manual).unwrap();
+        let order: Vec<(&str, i64)> = items
+            .iter()
+            .map(|item| (item.description.as_str(), item.position))
+            .collect();
+        assert_eq!(order, [("first", 1), ("second", 2), ("third", 3)]);
+        assert_eq!(renumber_positions(&connection).unwrap(), 0);
+    }
+
+    #[test]
+    fn modified_sort_puts_the_latest_edit_first() {
+        let connection = open();
+        let first = add_task(&connection, "first", &[], None, None, now()).unwrap();
+        add_task(&connection, "second", &[], None, None, now()).unwrap();
+        set_priority(
+            &connection,
+            first,
+            Priority::High,
+            now() + chrono::TimeDelta::minutes(5),
+        )
+        .unwrap();
+
+        let items = load_tasks(&connection, None, SortMode::Modified).unwrap();
+        assert_eq!(items[0].description, "first");
+    }
+
+    #[test]
+    fn added_task_round_trips_through_load() {
+        let connection = open();
+        let tags = vec!["#home".to_string(), "@phone".to_string()];
+        let id = add_task(&connection, "call mom", &tags, None, None, now()).unwrap();
+
+        let items = load_tasks(&connection, Some(false), SortMode::Manual).unwrap();
+
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.id, id);
+        assert_eq!(item.description, "call mom");
+        assert!(!item.is_done);
+        assert!(item.priority == Priority::Normal);
+        assert_eq!(item.tags, tags);
+        assert_eq!(item.created_at, Some(now()));
+        assert_eq!(item.position, 1);
+    }
+
+    #[test]
+    fn load_filters_by_archive() {
+        let connection = open();
+        add_task(&connection, "kept", &[], None, None, now()).unwrap();
+        let archived = add_task(&connection, "archived", &[], None, None, now()).unwrap();
+        set_archived(&connection, archived, true, now()).unwrap();
+
+        let count = |archived| {
+            load_tasks(&connection, archived, SortMode::Manual)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(count(Some(false)), 1);
+        assert_eq!(count(Some(true)), 1);
+        assert_eq!(count(None), 2);
+    }
+
+    #[test]
+    fn set_done_records_completion_time() {
+        let connection = open();
+        let id = add_task(&connection, "task", &[], None, None, now()).unwrap();
+
+        assert_eq!(set_done(&connection, id, true, now()).unwrap(), Some(now()));
+        let item = &load_tasks(&connection, None, SortMode::Manual).unwrap()[0];
+        assert!(item.is_done);
+        assert_eq!(item.completed_at, Some(now()));
+
+        assert_eq!(set_done(&connection, id, false, now()).unwrap(), None);
+        let item = &load_tasks(&connection, None, SortMode::Manual).unwrap()[0];
+        assert!(!item.is_done);
+        assert_eq!(item.completed_at, None);
+    }
+
+    #[test]
+    fn moving_to_the_ends_renumbers_positions() {
+        let connection = open();
+        let first = add_task(&connection, "first", &[], None, None, now()).unwrap();
+        let second = add_task(&connection, "second", &[], None, None, now()).unwrap();
+        let third = add_task(&connection, "third", &[], None, None, now()).unwrap();
+        let order = || -> Vec<(i64, i64)> {
+            load_tasks(&connection, None, SortMode::Manual)
+                .unwrap()
+                .into_iter()
+                .map(|item| (item.id, item.position))
+                .collect()
+        };
+
+        move_to_top(&connection, third).unwrap();
+        assert_eq!(order(), [(third, 1), (first, 2), (second, 3)]);
+
+        move_to_bottom(&connection, third).unwrap();
+        assert_eq!(order(), [(first, 1), (second, 2), (third, 3)]);
+    }
+
+    #[test]
+    fn set_done_many_only_touches_the_given_tasks() {
+        let connection = open();
+        let first = add_task(&connection, "first", &[], None, None, now()).unwrap();
+        let second = add_task(&connection, "second", &[], None, None, now()).unwrap();
+        let skipped = add_task(&connection, "skipped", &[], None, None, now()).unwrap();
+        set_done(&connection, second, true, now()).unwrap();
+
+        assert_eq!(
+            set_done_many(&connection, &[first, second], true, now()).unwrap(),
+            1
+        );
+        let done: Vec<(i64, bool)> = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .into_iter()
+            .map(|item| (item.id, item.is_done))
+            .collect();
+        assert_eq!(done, [(first, true), (second, true), (skipped, false)]);
+    }
+
+    #[test]
+    fn tasks_are_grouped_by_category_with_uncategorized_last() {
+        let connection = open();
+        add_task(&connection, "loose", &[], None, None, now()).unwrap();
+        add_task(&connection, "report", &[], Some("work"), None, now()).unwrap();
+        add_task(&connection, "groceries", &[], Some("Home"), None, now()).unwrap();
+        add_task(&connection, "review", &[], Some("work"), None, now()).unwrap();
+
+        let order: Vec<String> = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.description)
+            .collect();
+
+        assert_eq!(order, ["groceries", "report", "review", "loose"]);
+    }
+
+    #[test]
+    fn deleting_a_task_removes_its_tags() {
+        let connection = open();
+        let id = add_task(
+            &connection,
+            "task",
+            &["#tag".to_string()],
+            None,
+            None,
+            now(),
+        )
+        .unwrap();
+
+        delete_task(&connection, id).unwrap();
+
+        assert_eq!(task_count(&connection), 0);
+        assert_eq!(tag_count(&connection), 0);
+    }
+
+    #[test]
+    fn restore_puts_a_deleted_task_back() {
+        let connection = open();
+        let id = add_task(
+            &connection,
+            "task",
+            &["#tag".to_string()],
+            None,
+            None,
+            now(),
+        )
+        .unwrap();
+        let item = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .remove(0);
+        delete_task(&connection, id).unwrap();
+
+        restore_task(&connection, &item).unwrap();
+
+        let restored = &load_tasks(&connection, None, SortMode::Manual).unwrap()[0];
+        assert_eq!(restored.id, id);
+        assert_eq!(restored.tags, item.tags);
+    }
+
+    #[test]
+    fn adding_a_task_logs_it() {
+        let connection = open();
+        add_task(&connection, "water plants", &[], None, None, now()).unwrap();
+
+        let log: Vec<(Activity, String)> = load_activity(&connection)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.activity, entry.description))
+            .collect();
+        assert_eq!(log, [(Activity::Added, "water plants".to_string())]);
+    }
+
+    #[test]
+    fn completing_an_occurrence_archives_it_and_undo_reverses_it() {
+        let connection = open();
+        let id = add_task(&connection, "water plants", &[], None, None, now()).unwrap();
+        set_recurrence(&connection, id, Recurrence::Daily, now()).unwrap();
+        let item = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .remove(0);
+
+        let next_id = complete_occurrence(&connection, &item, now()).unwrap();
+
+        let open = load_tasks(&connection, Some(false), SortMode::Manual).unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, next_id);
+        assert!(open[0].recurrence == Recurrence::Daily);
+        assert!(open[0].due_date.is_some());
+
+        undo_occurrence(&connection, id, next_id).unwrap();
+
+        let open = load_tasks(&connection, Some(false), SortMode::Manual).unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, id);
+        assert!(!open[0].is_done);
+        assert!(open[0].recurrence == Recurrence::Daily);
+    }
+
+    #[test]
+    fn the_next_occurrence_keeps_its_blocker() {
+        let connection = open();
+        let blocker = add_task(&connection, "buy seeds", &[], None, None, now()).unwrap();
+        let id = add_task(&connection, "sow seeds", &[], None, None, now()).unwrap();
+        set_recurrence(&connection, id, Recurrence::Weekly, now()).unwrap();
+        set_blocked_by(&connection, id, Some(blocker), now()).unwrap();
+        set_done(&connection, blocker, true, now()).unwrap();
+        let item = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .into_iter()
+            .find(|item| item.id == id)
+            .unwrap();
+
+        let next_id = complete_occurrence(&connection, &item, now()).unwrap();
+
+        let next = load_tasks(&connection, Some(false), SortMode::Manual)
+            .unwrap()
+            .into_iter()
+            .find(|item| item.id == next_id)
+            .unwrap();
+        assert_eq!(next.blocked_by, Some(blocker));
+    }
+
+    #[test]
+    fn duplicate_check_ignores_case_and_completed_tasks() {
+        let connection = open();
+        add_task(&connection, "Buy milk", &[], None, None, now()).unwrap();
+        let done = add_task(&connection, "walk dog", &[], None, None, now()).unwrap();
+        set_done(&connection, done, true, now()).unwrap();
+
+        assert!(has_incomplete_duplicate(&connection, "buy MILK").unwrap());
+        assert!(!has_incomplete_duplicate(&connection, "walk dog").unwrap());
+    }
+
+    #[test]
+    fn duplicate_check_folds_non_ascii_case() {
+        let connection = open();
+        add_task(&connection, "Äpfel kaufen", &[], None, None, now()).unwrap();
+
+        assert!(has_incomplete_duplicate(&connection, "äpfel kaufen").unwrap());
+    }
+
+    #[test]
+    fn failed_transaction_rolls_back_every_statement() {
+        let mut connection = open();
+
+        let result: Result<()> = with_transaction(&mut connection, |tx| {
+            tx.execute("INSERT INTO tasks (description) VALUES ('first')", [])?;
+            tx.execute("INSERT INTO tasks (description) VALUES ('second')", [])?;
+            tx.execute(
+                "INSERT INTO tasks (id, description) VALUES (1, 'duplicate')",
+                [],
+            )?;
+            Ok(())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(task_count(&connection), 0);
+    }
+
+    #[test]
+    fn import_failing_mid_batch_inserts_nothing() {
+        let mut connection = open();
+        add_task(
+            &connection,
+            "existing",
+            &["#keep".to_string()],
+            None,
+            None,
+            now(),
+        )
+        .unwrap();
+        connection
+            .execute_batch(
+                "CREATE TRIGGER reject_bad BEFORE INSERT ON tasks WHEN NEW.description = 'bad'
+                 BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+            )
+            .unwrap();
+        let tasks: Vec<ImportedTask> = serde_json::from_str(
+            r##"[
+                {"description": "good", "tags": ["#new"]},
+                {"description": "bad"},
+                {"description": "never reached"}
+            ]"##,
+        )
+        .unwrap();
+
+        let result = with_transaction(&mut connection, |tx| {
+            import_tasks(tx, &tasks, ImportMode::Replace)
+        });
+
+        assert!(result.is_err());
+        let descriptions: Vec<String> = load_tasks(&connection, None, SortMode::Manual)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.description)
+            .collect();
+        assert_eq!(descriptions, ["existing"]);
+        assert_eq!(tag_count(&connection), 1);
+    }
+
+    #[test]
+    fn merge_import_skips_existing_descriptions() {
+        let mut connection = open();
+        add_task(&connection, "Existing", &[], None, None, now()).unwrap();
+        let tasks: Vec<ImportedTask> =
+            serde_json::from_str(r#"[{"description": "existing"}, {"description": "new"}]"#)
+                .unwrap();
+
+        let imported = with_transaction(&mut connection, |tx| {
+            import_tasks(tx, &tasks, ImportMode::Merge)
+        })
+        .unwrap();
+
+        assert_eq!(imported, 1);
+        assert_eq!(task_count(&connection), 2);
+    }
+
+    #[test]
+    fn import_relinks_subtasks_and_blockers_to_the_new_ids() {
+        let mut connection = open();
+        add_task(&connection, "already here", &[], None, None, now()).unwrap();
+        let tasks: Vec<ImportedTask> = serde_json::from_str(
+            r#"[
+                {"id": 7, "description": "plan trip"},
+                {"id": 8, "description": "book flights", "parent_id": 7},
+                {"id": 9, "description": "pack", "blocked_by": 8},
+                {"id": 10, "description": "orphan", "parent_id": 42}
+            ]"#,
+        )
+        .unwrap();
+
+        with_transaction(&mut connection, |tx| {
+            import_tasks(tx, &tasks, ImportMode::Merge)
+        })
+        .unwrap();
+
+        let items = load_tasks(&connection, None, SortMode::Manual).unwrap();
+        let task = |description: &str| {
+            items
+                .iter()
+                .find(|item| item.description == description)
+                .unwrap()
+        };
+        assert_eq!(task("book flights").parent_id, Some(task("plan trip").id));
+        assert_eq!(task("pack").blocked_by, Some(task("book flights").id));
+        assert_eq!(task("orphan").parent_id, None);
+    }
+
+    #[test]
+    fn windows_page_through_the_sorted_tasks_with_full_counts() {
+        let connection = open();
+        let ids: Vec<i64> = (1..=10)
+            .map(|n| add_task(&connection, &format!("task {}", n), &[], None, None, now()).unwrap())
+            .collect();
+        set_done(&connection, ids[2], true, now()).unwrap();
+
+        let window: Vec<i64> = load_task_window(&connection, false, SortMode::Manual, 4, 3)
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(window, ids[4..7]);
+        assert_eq!(
+            task_position(&connection, false, SortMode::Manual, ids[7]).unwrap(),
+            Some(7)
+        );
+        assert_eq!(
+            task_position(&connection, false, SortMode::DoneLast, ids[2]).unwrap(),
+            Some(9)
+        );
+        assert_eq!(
+            task_position(&connection, true, SortMode::Manual, ids[7]).unwrap(),
+            None
+        );
+
+        let counts = task_counts(&connection, false, Some(now())).unwrap();
+        assert_eq!((counts.total, counts.done, counts.stale), (10, 1, 9));
+        assert_eq!(task_counts(&connection, false, None).unwrap().stale, 0);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Utc};
+use color_eyre::{Result, eyre::WrapErr};
+use serde::Deserialize;
+
+use crate::model::{
+    Priority, Recurrence, TaskColor, TaskStatus, TodoItem, parse_category, parse_tags,
+};
+
+pub const JSON_FILE: &str = "tasks.json";
+pub const MARKDOWN_FILE: &str = "tasks.md";
+pub const CSV_FILE: &str = "tasks.csv";
+
+/// Exports are written next to the database so they travel with it.
+pub fn export_path(data_dir: &Path, file_name: &str) -> PathBuf {
+    data_dir.join(file_name)
+}
+
+pub fn export_json(items: &[TodoItem], data_dir: &Path) -> Result<PathBuf> {
+    let path = export_path(data_dir, JSON_FILE);
+    let json = serde_json::to_string_pretty(items)?;
+    write_atomic(&path, json.as_bytes())?;
+    Ok(path)
+}
+
+pub fn export_markdown(items: &[TodoItem], data_dir: &Path) -> Result<PathBuf> {
+    let path = export_path(data_dir, MARKDOWN_FILE);
+    write_atomic(&path, render_markdown(items, Local::now()).as_bytes())?;
+    Ok(path)
+}
+
+pub fn export_csv(items: &[TodoItem], data_dir: &Path) -> Result<PathBuf> {
+    let path = export_path(data_dir, CSV_FILE);
+    write_atomic(&path, render_csv(items).as_bytes())?;
+    Ok(path)
+}
+
+fn render_csv(items: &[TodoItem]) -> String {
+    let mut csv = String::from("id,description,status,priority,due_date,created_at\r\n");
+    for item in items {
+        let status = match item.status {
+            TaskStatus::Todo => "todo",
+            TaskStatus::Doing => "doing",
+            TaskStatus::Done => "done",
+        };
+        let fields = [
+            item.id.to_string(),
+            csv_field(&item.description),
+            status.to_string(),
+            item.priority.label().to_lowercase(),
+            item.due_label().unwrap_or_default(),
+            item.created_at
+                .map_or(String::new(), |created_at| created_at.to_rfc3339()),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Quotes `value` when it holds a comma, quote or line break, doubling any quotes, as
+/// spreadsheets expect (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Lists the tasks under a heading per project, in the order the projects first appear,
+/// as the list groups them. There are no headings when no task has a project.
+fn render_markdown(items: &[TodoItem], now: DateTime<Local>) -> String {
+    let mut markdown = format!("# Tasks\n\n_Exported {}_\n\n", now.format("%Y-%m-%d %H:%M"));
+    let grouped = items.iter().any(|item| item.category.is_some());
+    let mut categories: Vec<Option<&str>> = Vec::new();
+    for item in items {
+        if !categories.contains(&item.category.as_deref()) {
+            categories.push(item.category.as_deref());
+        }
+    }
+    for (group, category) in categories.into_iter().enumerate() {
+        if grouped {
+            if group > 0 {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("## {}\n\n", category.unwrap_or("No project")));
+        }
+        for item in items
+            .iter()
+            .filter(|item| item.category.as_deref() == category)
+        {
+            let checkbox = if item.is_done { "[x]" } else { "[ ]" };
+            markdown.push_str(&format!("- {} {}\n", checkbox, item.description));
+        }
+    }
+    markdown
+}
+
+/// A task read back from a JSON export. Ids and positions are reassigned on import.
+#[derive(Deserialize)]
+pub struct ImportedTask {
+    /// The id in the exported list, only used to relink subtasks and blockers.
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub description: String,
+    #[serde(default)]
+    pub is_done: bool,
+    /// Only "doing" adds anything to `is_done`; older exports don't have it.
+    #[serde(default)]
+    pub status: TaskStatus,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub due_time: Option<NaiveTime>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Falls back to `created_at` for exports made before tasks tracked edits.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub recurrence: Recurrence,
+    #[serde(default)]
+    pub color: TaskColor,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub blocked_by: Option<i64>,
+}
+
+pub fn read_json(data_dir: &Path) -> Result<Vec<ImportedTask>> {
+    let path = export_path(data_dir, JSON_FILE);
+    let json =
+        fs::read_to_string(&path).wrap_err_with(|| format!("cannot read {}", path.display()))?;
+    let tasks =
+        serde_json::from_str(&json).wrap_err_with(|| format!("invalid {}", path.display()))?;
+    Ok(tasks)
+}
+
+/// Reads a hand-written list with one task per line, for `--import-file`. A leading `[x] `
+/// marks a task done, and blank lines and lines starting with `#` are skipped. Tags and a
+/// `project:` prefix are picked out as when typing a task.
+pub fn read_plaintext(path: &Path, now: DateTime<Utc>) -> Result<Vec<ImportedTask>> {
+    let text =
+        fs::read_to_string(path).wrap_err_with(|| format!("cannot read {}", path.display()))?;
+    Ok(parse_plaintext(&text, now))
+}
+
+fn parse_plaintext(text: &str, now: DateTime<Utc>) -> Vec<ImportedTask> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (is_done, line) = match line.strip_prefix("[x] ") {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix("[ ] ").unwrap_or(line)),
+            };
+            let (category, line) = parse_category(line);
+            let (description, tags) = parse_tags(line);
+            (!description.is_empty()).then(|| ImportedTask {
+                id: None,
+                description,
+                is_done,
+                status: TaskStatus::default(),
+                priority: Priority::default(),
+                due_date: None,
+                due_time: None,
+                created_at: Some(now),
+                completed_at: is_done.then_some(now),
+                updated_at: Some(now),
+                tags,
+                archived: false,
+                recurrence: Recurrence::None,
+                color: TaskColor::None,
+                notes: String::new(),
+                category,
+                pinned: false,
+                parent_id: None,
+                blocked_by: None,
+            })
+        })
+        .collect()
+}
+
+/// Writes to a sibling temp file and renames it over `path`, so a crash mid-write never
+/// leaves a truncated export behind.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_with_commas_or_quotes_are_quoted() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("milk, eggs"), "\"milk, eggs\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    fn task(description: &str, is_done: bool, category: Option<&str>) -> TodoItem {
+        TodoItem {
+            id: 0,
+            description: description.to_string(),
+            is_done,
+            status: if is_done {
+                TaskStatus::Done
+            } else {
+                TaskStatus::Todo
+            },
+            priority: Priority::default(),
+            due_date: None,
+            due_time: None,
+            position: 0,
+            created_at: None,
+            completed_at: None,
+            updated_at: None,
+            recurrence: Recurrence::None,
+            color: TaskColor::None,
+            tags: Vec::new(),
+            archived: false,
+            notes: String::new(),
+            category: category.map(str::to_string),
+            parent_id: None,
+            pinned: false,
+            blocked_by: None,
+            blocked: false,
+        }
+    }
+
+    #[test]
+    fn markdown_groups_tasks_under_project_headings() {
+        let now = Local::now();
+        let header = format!("# Tasks\n\n_Exported {}_\n\n", now.format("%Y-%m-%d %H:%M"));
+        let items = [
+            task("fix sink", false, Some("home")),
+            task("file taxes", true, Some("admin")),
+            task("buy milk", false, None),
+            task("paint fence", true, Some("home")),
+        ];
+
+        assert_eq!(
+            render_markdown(&items, now),
+            format!(
+                "{}## home\n\n- [ ] fix sink\n- [x] paint fence\n\n\
+                 ## admin\n\n- [x] file taxes\n\n\
+                 ## No project\n\n- [ ] buy milk\n",
+                header
+            )
+        );
+        assert_eq!(
+            render_markdown(&[task("buy milk", false, None)], now),
+            format!("{}- [ ] buy milk\n", header)
+        );
+    }
+
+    #[test]
+    fn parse_plaintext_reads_one_task_per_line() {
+        let now = Utc::now();
+        let text = "# groceries\n\nbuy milk #shop\n[x] call mom\n  [ ] project: home fix sink\n";
+
+        let tasks = parse_plaintext(text, now);
+        let summary: Vec<(&str, bool, Option<&str>)> = tasks
+            .iter()
+            .map(|task| {
+                (
+                    task.description.as_str(),
+                    task.is_done,
+                    task.category.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("buy milk", false, None),
+                ("call mom", true, None),
+                ("fix sink", false, Some("home")),
+            ]
+        );
+        assert_eq!(tasks[0].tags, ["#shop"]);
+        assert_eq!(tasks[1].completed_at, Some(now));
+    }
+}
@@ -0,0 +1,137 @@
+/// Points for each matched character.
+const MATCH: i64 = 1;
+/// Bonus for a character matched right after the previous one.
+const CONSECUTIVE: i64 = 5;
+/// Bonus for a character matched at the start of a word.
+const WORD_START: i64 = 3;
+/// Penalty for each break between matched characters.
+const GAP: i64 = 1;
+
+/// Where and how well a query matched some text.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// Byte offsets of the matched characters in the text.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `query` as a case-insensitive subsequence of `text`, so "tmgr" matches
+/// "task manager". Runs of consecutive characters and matches at word starts score higher.
+///
+/// Every place the first query character occurs is tried as a starting point and the best
+/// score kept, since a single greedy pass would split a run like "man" in "command manager".
+/// An empty query matches everything with a score of zero.
+pub fn fuzzy_match(text: &str, query: &str) -> Option<Match> {
+    let text: Vec<(usize, char)> = text.char_indices().collect();
+    let query: Vec<char> = query.chars().collect();
+    let Some(&first) = query.first() else {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    };
+
+    let mut best: Option<Match> = None;
+    for start in (0..text.len()).filter(|&start| same_letter(text[start].1, first)) {
+        if let Some(found) = match_from(&text, &query, start)
+            && best.as_ref().is_none_or(|best| found.score > best.score)
+        {
+            best = Some(found);
+        }
+    }
+    best
+}
+
+/// Greedily matches `query` against `text` from the character at `start`.
+fn match_from(text: &[(usize, char)], query: &[char], start: usize) -> Option<Match> {
+    let mut score = 0;
+    let mut positions = Vec::with_capacity(query.len());
+    let mut previous: Option<usize> = None;
+    let mut remaining = query.iter().peekable();
+
+    for (index, &(offset, c)) in text.iter().enumerate().skip(start) {
+        let Some(&&wanted) = remaining.peek() else {
+            break;
+        };
+        if !same_letter(c, wanted) {
+            continue;
+        }
+        remaining.next();
+
+        score += MATCH;
+        match previous {
+            Some(previous) if previous + 1 == index => score += CONSECUTIVE,
+            Some(_) => score -= GAP,
+            None => {}
+        }
+        if index == 0 || !text[index - 1].1.is_alphanumeric() {
+            score += WORD_START;
+        }
+        positions.push(offset);
+        previous = Some(index);
+    }
+
+    remaining
+        .peek()
+        .is_none()
+        .then_some(Match { score, positions })
+}
+
+fn same_letter(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank<'a>(texts: &[&'a str], query: &str) -> Vec<&'a str> {
+        let mut matches: Vec<(&str, i64)> = texts
+            .iter()
+            .filter_map(|text| fuzzy_match(text, query).map(|found| (*text, found.score)))
+            .collect();
+        matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        matches.into_iter().map(|(text, _)| text).collect()
+    }
+
+    #[test]
+    fn matches_a_subsequence_ignoring_case() {
+        let found = fuzzy_match("Task Manager", "tmgr").unwrap();
+        assert_eq!(found.positions, [0, 5, 9, 11]);
+    }
+
+    #[test]
+    fn rejects_letters_out_of_order() {
+        assert_eq!(fuzzy_match("task manager", "rgmt"), None);
+        assert_eq!(fuzzy_match("task", "tasks"), None);
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        assert_eq!(fuzzy_match("anything", "").unwrap().score, 0);
+    }
+
+    #[test]
+    fn finds_the_best_run_rather_than_the_first_letters() {
+        let found = fuzzy_match("command manager", "man").unwrap();
+        assert_eq!(found.positions, [8, 9, 10]);
+    }
+
+    #[test]
+    fn positions_are_byte_offsets() {
+        let found = fuzzy_match("café bar", "éb").unwrap();
+        assert_eq!(found.positions, [3, 6]);
+    }
+
+    #[test]
+    fn ranks_runs_and_word_starts_first() {
+        assert_eq!(
+            rank(&["human resources", "tidy garage", "manager sync"], "man"),
+            ["manager sync", "human resources"]
+        );
+        assert_eq!(
+            rank(&["manager", "water plants", "mgr report"], "mgr"),
+            ["mgr report", "manager"]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single-line text buffer with a cursor.
+///
+/// The cursor is a byte offset that is always kept on a `char` boundary, so
+/// editing never splits a multibyte character.
+#[derive(Default)]
+pub struct TextInput {
+    text: String,
+    cursor: usize,
+}
+
+impl TextInput {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replaces the contents and places the cursor at the end.
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    /// The text before and after the cursor.
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.text.split_at(self.cursor)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// The run of non-whitespace text just before the cursor, empty after a space.
+    pub fn word_before_cursor(&self) -> &str {
+        let before = &self.text[..self.cursor];
+        before
+            .rfind(char::is_whitespace)
+            .map_or(before, |index| before[index..].trim_start())
+    }
+
+    /// Swaps the word before the cursor for `word`, leaving the cursor after it.
+    pub fn replace_word_before_cursor(&mut self, word: &str) {
+        let start = self.cursor - self.word_before_cursor().len();
+        self.text.replace_range(start..self.cursor, word);
+        self.cursor = start + word.len();
+    }
+
+    /// Deletes the character before the cursor.
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the character under the cursor, doing nothing at the end of the text.
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Deletes the word before the cursor along with any whitespace between them.
+    pub fn delete_word(&mut self) {
+        let before = self.text[..self.cursor].trim_end();
+        let start = before
+            .split_word_bound_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Applies an editing key, returning whether the text changed.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let len = self.text.len();
+                self.delete_word();
+                len != self.text.len()
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let changed = !self.text.is_empty();
+                self.clear();
+                changed
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.insert(c);
+                true
+            }
+            KeyCode::Backspace => {
+                let len = self.text.len();
+                self.backspace();
+                len != self.text.len()
+            }
+            KeyCode::Delete => {
+                let len = self.text.len();
+                self.delete();
+                len != self.text.len()
+            }
+            // Whitespace is collapsed when a task is saved, so a literal tab would only
+            // survive as a space anyway.
+            KeyCode::Tab => {
+                self.insert(' ');
+                true
+            }
+            KeyCode::Left => {
+                self.move_left();
+                false
+            }
+            KeyCode::Right => {
+                self.move_right();
+                false
+            }
+            KeyCode::Home => {
+                self.move_home();
+                false
+            }
+            KeyCode::End => {
+                self.move_end();
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(input: &mut TextInput, code: KeyCode) -> bool {
+        input.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn delete_removes_the_character_under_the_cursor() {
+        let mut input = TextInput::default();
+        input.set("naïve café".to_string());
+        input.move_home();
+        input.move_right();
+        input.move_right();
+        assert!(press(&mut input, KeyCode::Delete));
+        assert_eq!(input.split_at_cursor(), ("na", "ve café"));
+
+        input.move_end();
+        input.move_left();
+        assert!(press(&mut input, KeyCode::Delete));
+        assert_eq!(input.as_str(), "nave caf");
+        assert!(!press(&mut input, KeyCode::Delete));
+        assert_eq!(input.as_str(), "nave caf");
+    }
+
+    #[test]
+    fn replaces_the_word_before_the_cursor() {
+        let mut input = TextInput::default();
+        input.set("call mum #ho today".to_string());
+        for _ in 0.." today".len() {
+            input.move_left();
+        }
+        assert_eq!(input.word_before_cursor(), "#ho");
+
+        input.replace_word_before_cursor("#home");
+        assert_eq!(input.split_at_cursor(), ("call mum #home", " today"));
+        input.insert(' ');
+        assert_eq!(input.word_before_cursor(), "");
+    }
+
+    #[test]
+    fn tab_types_a_space() {
+        let mut input = TextInput::default();
+        input.set("buy milk".to_string());
+        input.move_home();
+        assert!(press(&mut input, KeyCode::Tab));
+        assert_eq!(input.split_at_cursor(), (" ", "buy milk"));
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, fs, io::ErrorKind, path::Path};
+
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+pub const KEYMAP_FILE: &str = "keybindings.toml";
+
+/// Everything a key can do in normal mode. Visual mode reuses the motions, `visual` and
+/// `delete`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyAction {
+    Quit,
+    Add,
+    AddSubtask,
+    Edit,
+    Duplicate,
+    Yank,
+    OpenUrl,
+    Toggle,
+    ToggleAll,
+    StatusNext,
+    StatusPrevious,
+    Delete,
+    ClearCompleted,
+    Archive,
+    ArchiveView,
+    RaisePriority,
+    LowerPriority,
+    Down,
+    Up,
+    MoveDown,
+    MoveUp,
+    MoveTop,
+    MoveBottom,
+    PageDown,
+    PageUp,
+    Paged,
+    NextPage,
+    PreviousPage,
+    /// Pressed twice, like `gg`, to jump to the first task.
+    First,
+    Last,
+    NextIncomplete,
+    PreviousIncomplete,
+    Sort,
+    Theme,
+    DueDate,
+    SnoozeDay,
+    SnoozeWeek,
+    Recurrence,
+    Color,
+    Pin,
+    Detail,
+    Focus,
+    HideDone,
+    CollapseDone,
+    LineNumbers,
+    Today,
+    FilterHigh,
+    FilterNormal,
+    FilterLow,
+    Notes,
+    Editor,
+    Undo,
+    Redo,
+    Search,
+    ClearSearch,
+    Help,
+    CommandLine,
+    Stats,
+    Activity,
+    Lists,
+    TagFilter,
+    Visual,
+    ExportJson,
+    ExportMarkdown,
+    ExportCsv,
+    Import,
+}
+
+/// Each action with its name in `keybindings.toml` and its default keys.
+const DEFAULTS: &[(KeyAction, &str, &[&str])] = &[
+    (KeyAction::Quit, "quit", &["q", "Ctrl+c"]),
+    (KeyAction::Add, "add", &["a"]),
+    (KeyAction::AddSubtask, "add_subtask", &["o"]),
+    (KeyAction::Edit, "edit", &["e"]),
+    (KeyAction::Duplicate, "duplicate", &["y"]),
+    (KeyAction::Yank, "yank", &["Y"]),
+    (KeyAction::OpenUrl, "open_url", &["O"]),
+    (KeyAction::Toggle, "toggle", &["Space", "Enter"]),
+    (KeyAction::ToggleAll, "toggle_all", &["Ctrl+Space"]),
+    (KeyAction::StatusNext, "status_next", &["w"]),
+    (KeyAction::StatusPrevious, "status_previous", &["W"]),
+    (KeyAction::Delete, "delete", &["d"]),
+    (KeyAction::ClearCompleted, "clear_completed", &["C"]),
+    (KeyAction::Archive, "archive", &["A"]),
+    (KeyAction::ArchiveView, "archive_view", &["Ctrl+a"]),
+    (KeyAction::RaisePriority, "raise_priority", &["+"]),
+    (KeyAction::LowerPriority, "lower_priority", &["-"]),
+    (KeyAction::Down, "down", &["j", "Down"]),
+    (KeyAction::Up, "up", &["k", "Up"]),
+    (KeyAction::MoveDown, "move_down", &["J", "Ctrl+Down"]),
+    (KeyAction::MoveUp, "move_up", &["K", "Ctrl+Up"]),
+    (KeyAction::MoveTop, "move_top", &["Ctrl+k"]),
+    (KeyAction::MoveBottom, "move_bottom", &["Ctrl+j"]),
+    (KeyAction::PageDown, "page_down", &["PageDown", "Ctrl+d"]),
+    (KeyAction::PageUp, "page_up", &["PageUp", "Ctrl+u"]),
+    (KeyAction::Paged, "paged", &["P"]),
+    (KeyAction::NextPage, "next_page", &["Ctrl+f"]),
+    (KeyAction::PreviousPage, "previous_page", &["Ctrl+b"]),
+    (KeyAction::First, "first", &["g"]),
+    (KeyAction::Last, "last", &["G"]),
+    (KeyAction::NextIncomplete, "next_incomplete", &["]"]),
+    (KeyAction::PreviousIncomplete, "previous_incomplete", &["["]),
+    (KeyAction::Sort, "sort", &["s"]),
+    (KeyAction::Theme, "theme", &["t"]),
+    (KeyAction::DueDate, "due_date", &["D"]),
+    (KeyAction::SnoozeDay, "snooze_day", &[">"]),
+    (KeyAction::SnoozeWeek, "snooze_week", &["Alt+>"]),
+    (KeyAction::Recurrence, "recurrence", &["R"]),
+    (KeyAction::Color, "color", &["c"]),
+    (KeyAction::Pin, "pin", &["p"]),
+    (KeyAction::Detail, "detail", &["i"]),
+    (KeyAction::Focus, "focus", &["z"]),
+    (KeyAction::HideDone, "hide_done", &["f"]),
+    (KeyAction::CollapseDone, "collapse_done", &["Z"]),
+    (KeyAction::LineNumbers, "line_numbers", &["L"]),
+    (KeyAction::Today, "today", &["Ctrl+t"]),
+    (KeyAction::FilterHigh, "filter_high", &["Alt+1"]),
+    (KeyAction::FilterNormal, "filter_normal", &["Alt+2"]),
+    (KeyAction::FilterLow, "filter_low", &["Alt+3"]),
+    (KeyAction::Notes, "notes", &["n"]),
+    (KeyAction::Editor, "editor", &["E"]),
+    (KeyAction::Undo, "undo", &["u"]),
+    (KeyAction::Redo, "redo", &["Ctrl+r"]),
+    (KeyAction::Search, "search", &["/"]),
+    (KeyAction::ClearSearch, "clear_search", &["Esc"]),
+    (KeyAction::Help, "help", &["?"]),
+    (KeyAction::CommandLine, "command_line", &[":"]),
+    (KeyAction::Stats, "stats", &["S"]),
+    (KeyAction::Activity, "activity", &["H"]),
+    (KeyAction::Lists, "lists", &["l"]),
+    (KeyAction::TagFilter, "tag_filter", &["T"]),
+    (KeyAction::Visual, "visual", &["v"]),
+    (KeyAction::ExportJson, "export_json", &["x"]),
+    (KeyAction::ExportMarkdown, "export_markdown", &["M"]),
+    (KeyAction::ExportCsv, "export_csv", &["X"]),
+    (KeyAction::Import, "import", &["I"]),
+];
+
+/// One key or a list of keys bound to an action.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// A key with the modifiers that matter for lookups. Shift is folded into the character
+/// for printable keys, so `J` and `Shift+j` are the same binding.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Self {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+
+    /// Parses names like `j`, `Ctrl+d`, `Alt+Enter` or `PageDown`.
+    fn parse(name: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = name;
+        while rest.len() > 1
+            && let Some((prefix, tail)) = rest.split_once('+')
+            && !tail.is_empty()
+        {
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier '{}' in key '{}'", prefix, name),
+            };
+            rest = tail;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_digit() && modifiers.is_empty() => {
+                bail!("'{}' is reserved for count prefixes", name)
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                _ => bail!("unknown key '{}'", name),
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+/// Maps pressed keys to actions, built from the defaults and `keybindings.toml`.
+pub struct KeyMap {
+    bindings: HashMap<KeyPress, KeyAction>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::build(HashMap::new()).expect("default keybindings are valid")
+    }
+}
+
+impl KeyMap {
+    /// Loads `keybindings.toml` from `data_dir`. Actions it does not mention keep their
+    /// default keys, and actions it lists replace theirs.
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(KEYMAP_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("cannot read {}", path.display()));
+            }
+        };
+        let overrides: HashMap<String, Keys> =
+            toml::from_str(&contents).wrap_err_with(|| format!("invalid {}", path.display()))?;
+        Self::build(overrides).wrap_err_with(|| format!("invalid {}", path.display()))
+    }
+
+    fn build(mut overrides: HashMap<String, Keys>) -> Result<Self> {
+        if let Some(name) = overrides
+            .keys()
+            .find(|name| !DEFAULTS.iter().any(|(_, known, _)| known == name))
+        {
+            bail!("unknown action '{}'", name);
+        }
+
+        let mut bindings = HashMap::new();
+        let mut names = HashMap::new();
+        for &(action, name, defaults) in DEFAULTS {
+            let keys = match overrides.remove(name) {
+                Some(Keys::One(key)) => vec![key],
+                Some(Keys::Many(keys)) => keys,
+                None => defaults.iter().map(|key| key.to_string()).collect(),
+            };
+            for key in keys {
+                let press = KeyPress::parse(&key).wrap_err_with(|| format!("in '{}'", name))?;
+                if let Some(other) = names.insert(press, name) {
+                    bail!("'{}' is bound to both {} and {}", key, other, name);
+                }
+                bindings.insert(press, action);
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn action(&self, key: KeyEvent) -> Option<KeyAction> {
+        self.bindings
+            .get(&KeyPress::new(key.code, key.modifiers))
+            .copied()
+    }
+}
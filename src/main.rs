@@ -1,15 +1,216 @@
 mod app;
+mod browser;
+mod cli;
+mod clipboard;
+mod command;
+mod config;
+mod db;
+mod export;
+mod fuzzy;
+mod input;
+mod keymap;
+mod model;
 
 use app::App;
-use color_eyre::Result;
+use chrono::{Local, NaiveTime, TimeDelta, Utc};
+use cli::{Args, CliCommand};
+use color_eyre::{
+    Result,
+    eyre::{WrapErr, bail},
+};
+use config::Config;
+use crossterm::{
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute,
+    terminal::SetTitle,
+};
+use db::ImportMode;
+use model::{Priority, TaskColor, normalize_whitespace, parse_category, parse_tags};
+use rusqlite::Connection;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
 
+    let args = Args::parse()?;
+    if args.help {
+        println!("{}", Args::usage());
+        return Ok(());
+    }
+
+    let db_path = args.db_path();
+    let mut connection = if args.demo {
+        demo_connection()?
+    } else {
+        db::open(&db_path)?
+    };
+    if let Some(path) = &args.import_file {
+        db::init_schema(&connection).wrap_err("failed to initialize database")?;
+        let tasks = export::read_plaintext(path, Utc::now())?;
+        let imported = db::with_transaction(&mut connection, |tx| {
+            db::import_tasks(tx, &tasks, ImportMode::Merge)
+        })?;
+        println!(
+            "Imported {} of {} tasks from {}",
+            imported,
+            tasks.len(),
+            path.display()
+        );
+    }
+    if let Some(command) = &args.command {
+        db::init_schema(&connection).wrap_err("failed to initialize database")?;
+        return match command {
+            CliCommand::Add(input) => add_task(&mut connection, input, &args.data_dir()),
+            CliCommand::List => list_tasks(&connection),
+        };
+    }
+    let mut app = App::new(connection, args.data_dir())
+        .wrap_err_with(|| format!("failed to load {}", db_path.display()))?;
+    if args.demo {
+        app = app.demo();
+    }
+
     let terminal = ratatui::init();
-    let app_result = App::new()?.run(terminal);
+    execute!(std::io::stdout(), EnableMouseCapture, EnableBracketedPaste)?;
+    let restore_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = execute!(
+            std::io::stdout(),
+            DisableMouseCapture,
+            DisableBracketedPaste,
+            SetTitle("")
+        );
+        restore_hook(info);
+    }));
+    let app_result = app.run(terminal);
 
+    let _ = execute!(
+        std::io::stdout(),
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        SetTitle("")
+    );
     ratatui::restore();
 
-    app_result
+    let completed = app_result?;
+    if !completed.is_empty() {
+        println!(
+            "Completed {} task{} this session:",
+            completed.len(),
+            if completed.len() == 1 { "" } else { "s" }
+        );
+        for item in completed {
+            println!("  [x] {}", item.description);
+        }
+    }
+    Ok(())
+}
+
+/// `add <description>`: inserts a task the same way typing it in the app would.
+fn add_task(connection: &mut Connection, input: &str, data_dir: &std::path::Path) -> Result<()> {
+    let config = Config::load(data_dir)?;
+    let input = normalize_whitespace(input);
+    let (category, input) = parse_category(&input);
+    let (description, tags) = parse_tags(input);
+    if description.is_empty() {
+        bail!("task description cannot be empty");
+    }
+    let length = description.chars().count();
+    if length > config.max_description_length {
+        bail!(
+            "task description is {} characters, the limit is {}",
+            length,
+            config.max_description_length
+        );
+    }
+
+    let id = db::with_transaction(connection, |tx| {
+        db::add_task(
+            tx,
+            &description,
+            &tags,
+            category.as_deref(),
+            None,
+            Utc::now(),
+        )
+    })?;
+    println!("Added task {}: {}", id, description);
+    Ok(())
+}
+
+/// `list`: prints the tasks outside the archive in manual order, one per line, with subtasks
+/// indented under their parent.
+fn list_tasks(connection: &Connection) -> Result<()> {
+    let items = db::load_tasks(connection, Some(false), model::SortMode::Manual)?;
+    for item in &items {
+        let nested = item
+            .parent_id
+            .is_some_and(|parent_id| items.iter().any(|other| other.id == parent_id));
+        let indent = if nested { "  " } else { "" };
+        let mut line = format!("{}{} {}", indent, item.status.checkbox(), item.description);
+        for tag in &item.tags {
+            line.push_str(&format!(" {}", tag));
+        }
+        if let Some(due) = item.due_label() {
+            line.push_str(&format!(" (due {})", due));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// `--demo`: an in-memory database seeded with a few sample tasks, gone when the app exits.
+fn demo_connection() -> Result<Connection> {
+    let mut connection = Connection::open_in_memory()?;
+    db::init_schema(&connection).wrap_err("failed to initialize database")?;
+    let now = Utc::now();
+    let today = Local::now().date_naive();
+    db::with_transaction(&mut connection, |tx| {
+        let welcome = add_demo_task(tx, "Try the demo, nothing here is saved", None)?;
+        db::set_notes(
+            tx,
+            welcome,
+            "Press ? for every key binding.\nQuit and start again for a fresh set of tasks.",
+            now,
+        )?;
+
+        let report = add_demo_task(tx, "project: work Send the quarterly report #work", None)?;
+        db::set_priority(tx, report, Priority::High, now)?;
+        db::set_due_date(tx, report, Some(today), None, now)?;
+        for step in ["Collect the numbers", "Write the summary"] {
+            add_demo_task(tx, step, Some(report))?;
+        }
+
+        let call = add_demo_task(tx, "project: work Call the dentist @phone", None)?;
+        db::set_due_date(
+            tx,
+            call,
+            Some(today + TimeDelta::days(1)),
+            NaiveTime::from_hms_opt(9, 30, 0),
+            now,
+        )?;
+
+        let plants = add_demo_task(tx, "project: home Water the plants @home", None)?;
+        db::set_color(tx, plants, TaskColor::Green, now)?;
+        db::set_done(tx, plants, true, now)?;
+
+        let shelf = add_demo_task(tx, "project: home Fix the wobbly shelf", None)?;
+        db::set_priority(tx, shelf, Priority::Low, now)?;
+        db::set_due_date(tx, shelf, Some(today - TimeDelta::days(2)), None, now)?;
+        Ok(())
+    })?;
+    Ok(connection)
+}
+
+/// Adds a sample task from `input`, parsed the same way as typed input.
+fn add_demo_task(connection: &Connection, input: &str, parent_id: Option<i64>) -> Result<i64> {
+    let (category, input) = parse_category(input);
+    let (description, tags) = parse_tags(input);
+    db::add_task(
+        connection,
+        &description,
+        &tags,
+        category.as_deref(),
+        parent_id,
+        Utc::now(),
+    )
 }
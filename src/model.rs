@@ -0,0 +1,767 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{
+    DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    Utc, Weekday,
+};
+use serde::{Deserialize, Serialize};
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+/// The optional local time after a due date, as in `2024-03-01 14:30`.
+pub const DUE_TIME_FORMAT: &str = "%H:%M";
+
+/// Typed before a word at the start of a description to file the task under that project.
+pub const CATEGORY_PREFIX: &str = "project:";
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            0 => Priority::Low,
+            2 => Priority::High,
+            _ => Priority::Normal,
+        }
+    }
+
+    pub fn as_i64(self) -> i64 {
+        match self {
+            Priority::Low => 0,
+            Priority::Normal => 1,
+            Priority::High => 2,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+        }
+    }
+
+    pub fn raised(self) -> Self {
+        match self {
+            Priority::Low => Priority::Normal,
+            _ => Priority::High,
+        }
+    }
+
+    pub fn lowered(self) -> Self {
+        match self {
+            Priority::High => Priority::Normal,
+            _ => Priority::Low,
+        }
+    }
+}
+
+/// Where a task is in its workflow. `w` and `W` step through these, wrapping around.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    #[default]
+    Todo,
+    Doing,
+    Done,
+}
+
+impl TaskStatus {
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            1 => TaskStatus::Doing,
+            2 => TaskStatus::Done,
+            _ => TaskStatus::Todo,
+        }
+    }
+
+    pub fn as_i64(self) -> i64 {
+        match self {
+            TaskStatus::Todo => 0,
+            TaskStatus::Doing => 1,
+            TaskStatus::Done => 2,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "To do",
+            TaskStatus::Doing => "In progress",
+            TaskStatus::Done => "Done",
+        }
+    }
+
+    pub fn checkbox(self) -> &'static str {
+        match self {
+            TaskStatus::Todo => "[ ]",
+            TaskStatus::Doing => "[~]",
+            TaskStatus::Done => "[x]",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TaskStatus::Todo => TaskStatus::Doing,
+            TaskStatus::Doing => TaskStatus::Done,
+            TaskStatus::Done => TaskStatus::Todo,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        match self {
+            TaskStatus::Todo => TaskStatus::Done,
+            TaskStatus::Doing => TaskStatus::Todo,
+            TaskStatus::Done => TaskStatus::Doing,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    #[default]
+    None,
+    Daily,
+    Weekly,
+}
+
+impl Recurrence {
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "daily" => Recurrence::Daily,
+            "weekly" => Recurrence::Weekly,
+            _ => Recurrence::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Recurrence::None => "none",
+            Recurrence::Daily => "daily",
+            Recurrence::Weekly => "weekly",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Recurrence::None => Recurrence::Daily,
+            Recurrence::Daily => Recurrence::Weekly,
+            Recurrence::Weekly => Recurrence::None,
+        }
+    }
+
+    /// The due date of the next occurrence: one interval after `due` (or `today` when there
+    /// is no due date), advanced until it is in the future so late completions don't
+    /// spawn an already-overdue copy.
+    pub fn next_due(self, due: Option<NaiveDate>, today: NaiveDate) -> Option<NaiveDate> {
+        let interval = match self {
+            Recurrence::None => return due,
+            Recurrence::Daily => Days::new(1),
+            Recurrence::Weekly => Days::new(7),
+        };
+        let mut next = due.unwrap_or(today) + interval;
+        while next <= today {
+            next = next + interval;
+        }
+        Some(next)
+    }
+}
+
+/// A color swatch shown before a task, cycled with `c` through a small palette.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskColor {
+    #[default]
+    None,
+    Red,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Magenta,
+}
+
+impl TaskColor {
+    /// Reads a stored color name. Names this version doesn't know load as no color.
+    pub fn from_label(label: &str) -> Self {
+        match label {
+            "red" => TaskColor::Red,
+            "yellow" => TaskColor::Yellow,
+            "green" => TaskColor::Green,
+            "cyan" => TaskColor::Cyan,
+            "blue" => TaskColor::Blue,
+            "magenta" => TaskColor::Magenta,
+            _ => TaskColor::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TaskColor::None => "none",
+            TaskColor::Red => "red",
+            TaskColor::Yellow => "yellow",
+            TaskColor::Green => "green",
+            TaskColor::Cyan => "cyan",
+            TaskColor::Blue => "blue",
+            TaskColor::Magenta => "magenta",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TaskColor::None => TaskColor::Red,
+            TaskColor::Red => TaskColor::Yellow,
+            TaskColor::Yellow => TaskColor::Green,
+            TaskColor::Green => TaskColor::Cyan,
+            TaskColor::Cyan => TaskColor::Blue,
+            TaskColor::Blue => TaskColor::Magenta,
+            TaskColor::Magenta => TaskColor::None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum SortMode {
+    #[default]
+    Manual,
+    Id,
+    Priority,
+    Alphabetical,
+    Status,
+    Modified,
+    /// Manual order, with done tasks moved below the incomplete ones.
+    DoneLast,
+}
+
+impl SortMode {
+    pub fn next(self) -> Self {
+        match self {
+            SortMode::Manual => SortMode::Id,
+            SortMode::Id => SortMode::Priority,
+            SortMode::Priority => SortMode::Alphabetical,
+            SortMode::Alphabetical => SortMode::Status,
+            SortMode::Status => SortMode::Modified,
+            SortMode::Modified => SortMode::DoneLast,
+            SortMode::DoneLast => SortMode::Manual,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "manual" => Some(SortMode::Manual),
+            "id" => Some(SortMode::Id),
+            "priority" => Some(SortMode::Priority),
+            "alpha" | "alphabetical" | "a-z" => Some(SortMode::Alphabetical),
+            "status" => Some(SortMode::Status),
+            "modified" | "recent" => Some(SortMode::Modified),
+            "done-last" | "done_last" => Some(SortMode::DoneLast),
+            _ => None,
+        }
+    }
+
+    /// The name [`SortMode::from_name`] reads back, as typed after `:sort`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortMode::Manual => "manual",
+            SortMode::Id => "id",
+            SortMode::Priority => "priority",
+            SortMode::Alphabetical => "alpha",
+            SortMode::Status => "status",
+            SortMode::Modified => "modified",
+            SortMode::DoneLast => "done-last",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortMode::Manual => "Manual",
+            SortMode::Id => "Id",
+            SortMode::Priority => "Priority",
+            SortMode::Alphabetical => "A-Z",
+            SortMode::Status => "Status",
+            SortMode::Modified => "Modified",
+            SortMode::DoneLast => "Done last",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct TodoItem {
+    pub id: i64,
+    pub description: String,
+    pub is_done: bool,
+    /// Todo, doing or done. `is_done` is true exactly when this is `Done`.
+    pub status: TaskStatus,
+    pub priority: Priority,
+    pub due_date: Option<NaiveDate>,
+    /// The local time on `due_date` the task is due at, for appointments.
+    pub due_time: Option<NaiveTime>,
+    pub position: i64,
+    pub created_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the task was last added, edited or toggled.
+    pub updated_at: Option<DateTime<Utc>>,
+    pub recurrence: Recurrence,
+    pub color: TaskColor,
+    pub tags: Vec<String>,
+    pub archived: bool,
+    pub notes: String,
+    /// The project the task is grouped under in the list.
+    pub category: Option<String>,
+    /// The task this is a step of. Subtasks nest one level deep.
+    pub parent_id: Option<i64>,
+    /// Listed above every other task whatever the sort.
+    pub pinned: bool,
+    /// The task that has to be done before this one can be.
+    pub blocked_by: Option<i64>,
+    /// Whether `blocked_by` names a task that isn't done yet, resolved when loading.
+    pub blocked: bool,
+}
+
+impl TodoItem {
+    /// The description with its category prefixed and tags appended, as the user would
+    /// type it.
+    pub fn editable_text(&self) -> String {
+        let text = std::iter::once(self.description.as_str())
+            .chain(self.tags.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        match &self.category {
+            Some(category) => format!("{} {} {}", CATEGORY_PREFIX, category, text),
+            None => text,
+        }
+    }
+
+    /// The due date and time, with the time when one is set, as `YYYY-MM-DD HH:MM`.
+    pub fn due_label(&self) -> Option<String> {
+        self.due_date.map(|date| format_due(date, self.due_time))
+    }
+
+    /// Whether the task is unfinished and due at a time within `window` after `now`, local
+    /// time. Tasks with only a due date never are.
+    pub fn is_due_within(&self, now: NaiveDateTime, window: TimeDelta) -> bool {
+        match (self.due_date, self.due_time) {
+            (Some(date), Some(time)) if !self.is_done => {
+                let due = date.and_time(time);
+                due > now && due - now <= window
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.is_done && self.due_date.is_some_and(|due| due < today)
+    }
+
+    /// Whether the task is unfinished and was added at least `after_days` days ago. Never
+    /// true when `after_days` is zero.
+    pub fn is_stale(&self, now: DateTime<Utc>, after_days: u32) -> bool {
+        !self.is_done
+            && after_days > 0
+            && self
+                .created_at
+                .is_some_and(|created_at| now - created_at >= TimeDelta::days(after_days.into()))
+    }
+}
+
+/// What happened to a task, as recorded in the activity log.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Activity {
+    Added,
+    Completed,
+    Reopened,
+    Deleted,
+}
+
+impl Activity {
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "added" => Some(Activity::Added),
+            "completed" => Some(Activity::Completed),
+            "reopened" => Some(Activity::Reopened),
+            "deleted" => Some(Activity::Deleted),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Activity::Added => "added",
+            Activity::Completed => "completed",
+            Activity::Reopened => "reopened",
+            Activity::Deleted => "deleted",
+        }
+    }
+}
+
+/// One line of the activity log, with the description as it was at the time.
+pub struct ActivityEntry {
+    pub activity: Activity,
+    pub description: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Completion counts for one tag, shown in the statistics overlay.
+pub struct TagStats {
+    pub name: String,
+    pub done: i64,
+    pub total: i64,
+}
+
+/// How many tasks there are in or out of the archive, for when only some are loaded.
+#[derive(Clone, Copy, Default)]
+pub struct TaskCounts {
+    pub total: usize,
+    pub done: usize,
+    pub stale: usize,
+}
+
+/// The calendar days, in `timezone`, on which something was completed.
+pub fn completion_dates<Tz: TimeZone>(
+    completed_at: &[DateTime<Utc>],
+    timezone: &Tz,
+) -> HashSet<NaiveDate> {
+    completed_at
+        .iter()
+        .map(|timestamp| timestamp.with_timezone(timezone).date_naive())
+        .collect()
+}
+
+/// How many tasks were completed on each of the `days` days ending `today`, oldest first.
+/// Days without a completion are kept with a count of zero.
+pub fn completions_per_day<Tz: TimeZone>(
+    completed_at: &[DateTime<Utc>],
+    timezone: &Tz,
+    today: NaiveDate,
+    days: usize,
+) -> Vec<(NaiveDate, u64)> {
+    let mut counts: HashMap<NaiveDate, u64> = HashMap::new();
+    for timestamp in completed_at {
+        *counts
+            .entry(timestamp.with_timezone(timezone).date_naive())
+            .or_default() += 1;
+    }
+    (0..days as u64)
+        .rev()
+        .filter_map(|back| today.checked_sub_days(Days::new(back)))
+        .map(|day| (day, counts.get(&day).copied().unwrap_or(0)))
+        .collect()
+}
+
+/// The number of consecutive days with a completion ending today. A streak that reached
+/// yesterday still counts, since today's task may not be done yet.
+pub fn streak(dates: &HashSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = if dates.contains(&today) {
+        today
+    } else {
+        match today.pred_opt() {
+            Some(yesterday) => yesterday,
+            None => return 0,
+        }
+    };
+    let mut length = 0;
+    while dates.contains(&day) {
+        length += 1;
+        match day.pred_opt() {
+            Some(previous) => day = previous,
+            None => break,
+        }
+    }
+    length
+}
+
+/// Parses a due date typed or stored as `YYYY-MM-DD` or `YYYY-MM-DD HH:MM`.
+pub fn parse_due(text: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let text = text.trim();
+    let (date, time) = match text.split_once(' ') {
+        Some((date, time)) => (
+            date,
+            Some(NaiveTime::parse_from_str(time.trim(), DUE_TIME_FORMAT).ok()?),
+        ),
+        None => (text, None),
+    };
+    Some((NaiveDate::parse_from_str(date, DATE_FORMAT).ok()?, time))
+}
+
+/// Parses a due date typed into the prompt: a phrase [`parse_relative_date`] understands or
+/// a date as [`parse_due`] reads it, either optionally followed by an `HH:MM` time.
+pub fn parse_due_input(text: &str, today: NaiveDate) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let text = text.trim();
+    let (phrase, time) = text
+        .rsplit_once(' ')
+        .and_then(|(phrase, time)| {
+            let time = NaiveTime::parse_from_str(time, DUE_TIME_FORMAT).ok()?;
+            Some((phrase, Some(time)))
+        })
+        .unwrap_or((text, None));
+    match parse_relative_date(phrase, today) {
+        Some(date) => Some((date, time)),
+        None => parse_due(text),
+    }
+}
+
+/// Resolves "today", "tomorrow", a weekday ("fri", "next friday"), "next week",
+/// "next month" or "in N days/weeks/months" to a date counted from `today`. A weekday is
+/// the next one after today, so "mon" on a Monday is a week away.
+pub fn parse_relative_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words[..] {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["next", "week"] => today.checked_add_days(Days::new(7)),
+        ["next", "month"] => today.checked_add_months(Months::new(1)),
+        ["next", day] | [day] => {
+            let weekday: Weekday = day.parse().ok()?;
+            let ahead = (weekday.days_since(today.weekday()) + 6) % 7 + 1;
+            today.checked_add_days(Days::new(ahead.into()))
+        }
+        ["in", count, unit] => {
+            let count: u32 = count.parse().ok()?;
+            match unit.strip_suffix('s').unwrap_or(unit) {
+                "day" => today.checked_add_days(Days::new(count.into())),
+                "week" => today.checked_add_days(Days::new(u64::from(count) * 7)),
+                "month" => today.checked_add_months(Months::new(count)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Writes a due date the way [`parse_due`] reads it.
+pub fn format_due(date: NaiveDate, time: Option<NaiveTime>) -> String {
+    match time {
+        Some(time) => format!(
+            "{} {}",
+            date.format(DATE_FORMAT),
+            time.format(DUE_TIME_FORMAT)
+        ),
+        None => date.format(DATE_FORMAT).to_string(),
+    }
+}
+
+/// Trims `input` and collapses every run of whitespace inside it to a single space.
+pub fn normalize_whitespace(input: &str) -> String {
+    input.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Splits a leading `project: name` off `input`, returning the category and the rest. The
+/// category is the single word after the prefix, which may also be written `project:name`.
+pub fn parse_category(input: &str) -> (Option<String>, &str) {
+    let trimmed = input.trim_start();
+    let Some(rest) = trimmed
+        .get(..CATEGORY_PREFIX.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(CATEGORY_PREFIX))
+        .map(|_| trimmed[CATEGORY_PREFIX.len()..].trim_start())
+    else {
+        return (None, input);
+    };
+    let (name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if name.is_empty() || rest.trim().is_empty() {
+        return (None, input);
+    }
+    (Some(name.to_string()), rest.trim_start())
+}
+
+/// Splits `#tag` and `@context` words out of `input`, returning the remaining description
+/// and the (lowercased, de-duplicated) tags. Input made only of tags is kept as the description.
+pub fn parse_tags(input: &str) -> (String, Vec<String>) {
+    let (tags, words): (Vec<&str>, Vec<&str>) = input
+        .split_whitespace()
+        .partition(|word| word.len() > 1 && (word.starts_with('#') || word.starts_with('@')));
+
+    let mut tags: Vec<String> = tags.into_iter().map(str::to_lowercase).collect();
+    tags.sort();
+    tags.dedup();
+
+    let description = if words.is_empty() {
+        input.trim().to_string()
+    } else {
+        words.join(" ")
+    };
+    (description, tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap()
+    }
+
+    fn dates(list: &[&str]) -> HashSet<NaiveDate> {
+        list.iter().map(|s| date(s)).collect()
+    }
+
+    fn timestamp(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_due_takes_a_date_with_an_optional_time() {
+        let time = NaiveTime::from_hms_opt(14, 30, 0);
+        assert_eq!(parse_due("2024-03-01"), Some((date("2024-03-01"), None)));
+        assert_eq!(
+            parse_due(" 2024-03-01 14:30 "),
+            Some((date("2024-03-01"), time))
+        );
+        assert_eq!(format_due(date("2024-03-01"), time), "2024-03-01 14:30");
+
+        for malformed in [
+            "2024-03-01 25:00",
+            "2024-03-01 2pm",
+            "2024-13-01",
+            "tomorrow",
+            "",
+        ] {
+            assert_eq!(parse_due(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn relative_dates_count_from_today() {
+        // A Wednesday.
+        let today = date("2024-03-06");
+        for (phrase, expected) in [
+            ("today", "2024-03-06"),
+            ("Tomorrow", "2024-03-07"),
+            ("fri", "2024-03-08"),
+            ("monday", "2024-03-11"),
+            ("wed", "2024-03-13"),
+            ("next fri", "2024-03-08"),
+            ("next week", "2024-03-13"),
+            ("next month", "2024-04-06"),
+            ("in 3 days", "2024-03-09"),
+            ("in 1 day", "2024-03-07"),
+            ("in 2 weeks", "2024-03-20"),
+            ("in 1 month", "2024-04-06"),
+        ] {
+            assert_eq!(
+                parse_relative_date(phrase, today),
+                Some(date(expected)),
+                "{}",
+                phrase
+            );
+        }
+        for unknown in ["someday", "in three days", "in 3 fortnights", "next", ""] {
+            assert_eq!(parse_relative_date(unknown, today), None, "{}", unknown);
+        }
+    }
+
+    #[test]
+    fn due_input_takes_phrases_or_dates_with_an_optional_time() {
+        let today = date("2024-03-06");
+        let time = NaiveTime::from_hms_opt(9, 30, 0);
+        assert_eq!(
+            parse_due_input("tomorrow 09:30", today),
+            Some((date("2024-03-07"), time))
+        );
+        assert_eq!(
+            parse_due_input("2024-05-01 09:30", today),
+            Some((date("2024-05-01"), time))
+        );
+        assert_eq!(
+            parse_due_input(" fri ", today),
+            Some((date("2024-03-08"), None))
+        );
+        assert_eq!(parse_due_input("tomorrow 9am", today), None);
+    }
+
+    #[test]
+    fn parse_category_takes_the_word_after_the_prefix() {
+        assert_eq!(
+            parse_category("project: work write report"),
+            (Some("work".to_string()), "write report")
+        );
+        assert_eq!(
+            parse_category("Project:home  call plumber"),
+            (Some("home".to_string()), "call plumber")
+        );
+    }
+
+    #[test]
+    fn parse_category_leaves_other_input_alone() {
+        assert_eq!(parse_category("buy milk"), (None, "buy milk"));
+        assert_eq!(parse_category("project: work"), (None, "project: work"));
+        assert_eq!(
+            parse_category("my project: work"),
+            (None, "my project: work")
+        );
+    }
+
+    #[test]
+    fn streak_counts_consecutive_days_ending_today() {
+        let completed = dates(&["2024-03-01", "2024-02-29", "2024-02-28"]);
+        assert_eq!(streak(&completed, date("2024-03-01")), 3);
+    }
+
+    #[test]
+    fn streak_stops_at_a_gap() {
+        let completed = dates(&["2024-03-01", "2024-02-29", "2024-02-27", "2024-02-26"]);
+        assert_eq!(streak(&completed, date("2024-03-01")), 2);
+    }
+
+    #[test]
+    fn streak_survives_until_today_is_over() {
+        let completed = dates(&["2024-02-29", "2024-02-28"]);
+        assert_eq!(streak(&completed, date("2024-03-01")), 2);
+    }
+
+    #[test]
+    fn streak_is_zero_after_a_missed_day() {
+        let completed = dates(&["2024-02-28", "2024-02-27"]);
+        assert_eq!(streak(&completed, date("2024-03-01")), 0);
+        assert_eq!(streak(&HashSet::new(), date("2024-03-01")), 0);
+    }
+
+    #[test]
+    fn completions_near_midnight_use_the_local_day() {
+        let completed = [
+            timestamp("2024-02-29T22:30:00Z"),
+            timestamp("2024-02-29T23:30:00Z"),
+        ];
+
+        let utc = completion_dates(&completed, &Utc);
+        assert_eq!(utc, dates(&["2024-02-29"]));
+        assert_eq!(streak(&utc, date("2024-03-01")), 1);
+
+        let ahead = FixedOffset::east_opt(2 * 3600).unwrap();
+        let local = completion_dates(&completed, &ahead);
+        assert_eq!(local, dates(&["2024-03-01"]));
+
+        let split = completion_dates(&completed, &FixedOffset::east_opt(3600).unwrap());
+        assert_eq!(split, dates(&["2024-02-29", "2024-03-01"]));
+        assert_eq!(streak(&split, date("2024-03-01")), 2);
+    }
+
+    #[test]
+    fn completions_per_day_keeps_empty_days() {
+        let completed = [
+            timestamp("2024-02-27T09:00:00Z"),
+            timestamp("2024-02-29T09:00:00Z"),
+            timestamp("2024-02-29T17:00:00Z"),
+            timestamp("2024-02-20T09:00:00Z"),
+        ];
+
+        assert_eq!(
+            completions_per_day(&completed, &Utc, date("2024-03-01"), 4),
+            [
+                (date("2024-02-27"), 1),
+                (date("2024-02-28"), 0),
+                (date("2024-02-29"), 2),
+                (date("2024-03-01"), 0),
+            ]
+        );
+        assert!(completions_per_day(&completed, &Utc, date("2024-03-01"), 0).is_empty());
+    }
+}